@@ -0,0 +1,104 @@
+use crate::config::{ConfigLocation, DryRunOutput};
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+/// Receive notifications when new Discord quests are released
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+    /// TOML config file to read (default: $QUEST_CONFIG). Environment variables override it.
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Profile from the config file applied on top of [default] (default: $QUEST_PROFILE)
+    #[arg(long, global = true, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Render notifications to stdout instead of sending them, and leave the state
+    /// file, feeds and calendar untouched (run and once only)
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// Like --dry-run, but write one JSON file per notification into this directory
+    #[arg(long, global = true, value_name = "DIR")]
+    pub dry_run_dir: Option<PathBuf>,
+
+    /// What to do (default: run)
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Poll for quests continuously and send notifications (the default)
+    Run,
+    /// Poll every configured account and region once, announce quests not seen by a previous run and exit (for cron)
+    Once,
+    /// Print the quests currently available
+    List {
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+        /// Region to query (default: the account's first region)
+        #[arg(long)]
+        region: Option<String>,
+        /// Account to query with (default: the first configured account)
+        #[arg(long)]
+        account: Option<String>,
+    },
+    /// Print the details of a single quest
+    Show {
+        quest_id: String,
+        /// Print the raw quest and computed fields as JSON
+        #[arg(long)]
+        json: bool,
+        /// Region to query (default: the account's first region)
+        #[arg(long)]
+        region: Option<String>,
+        /// Account to query with (default: the first configured account)
+        #[arg(long)]
+        account: Option<String>,
+    },
+    /// Print the Discord webhook JSON for a quest without sending it
+    Preview {
+        quest_id: String,
+        /// Region to query (default: the account's first region)
+        #[arg(long)]
+        region: Option<String>,
+        /// Account to query with (default: the first configured account)
+        #[arg(long)]
+        account: Option<String>,
+    },
+    /// Send a sample quest to every configured destination
+    TestWebhook,
+    /// Load and validate the configuration, then print a summary
+    ValidateConfig,
+    /// Print a systemd unit that runs this binary as a `Type=notify` service
+    PrintSystemdUnit,
+}
+
+impl Cli {
+    #[must_use]
+    pub fn config_location(&self) -> ConfigLocation {
+        ConfigLocation {
+            path: self.config.clone(),
+            profile: self.profile.clone(),
+        }
+    }
+
+    /// Where rendered notifications go, if this is a dry run
+    #[must_use]
+    pub fn dry_run(&self) -> Option<DryRunOutput> {
+        match &self.dry_run_dir {
+            Some(dir) => Some(DryRunOutput::Dir(dir.clone())),
+            None if self.dry_run => Some(DryRunOutput::Stdout),
+            None => None,
+        }
+    }
+
+    /// The selected subcommand, defaulting to `run`
+    #[must_use]
+    pub fn command(&self) -> Command {
+        self.command.clone().unwrap_or(Command::Run)
+    }
+}
@@ -1,6 +1,7 @@
 use crate::config::AppConfig;
 use crate::types::constants::{COLLECTIBLES_API_URL, REWARD_TYPE_COLLECTIBLE, build_cdn_url};
 use crate::types::quest::{CollectibleProduct, DiscordQuest, QuestReward};
+use crate::types::secret::body_excerpt;
 use reqwest::Client;
 use std::path::Path;
 
@@ -10,7 +11,7 @@ async fn fetch_collectible_icon(client: &Client, config: &AppConfig, sku_id: &st
     tracing::debug!("Fetching collectible product for SKU: {}", sku_id);
     
     let request = client.get(&url)
-        .header("Authorization", &config.discord_auth_token)
+        .header("Authorization", config.primary_account().token.expose())
         .timeout(std::time::Duration::from_secs(10));
     
     match request.send().await {
@@ -25,7 +26,8 @@ async fn fetch_collectible_icon(client: &Client, config: &AppConfig, sku_id: &st
                         status
                     );
                 } else {
-                    let body = resp.text().await.unwrap_or_else(|_| "<unable to read response body>".to_string());
+                    let body = resp.text().await
+                        .map_or_else(|_| "<unable to read response body>".to_string(), |body| body_excerpt(&body, &config.primary_account().token));
                     tracing::warn!("Failed to fetch collectible (status {}): {}", status, body);
                 }
                 return None;
@@ -42,12 +44,12 @@ async fn fetch_collectible_icon(client: &Client, config: &AppConfig, sku_id: &st
                     tracing::warn!("Collectible product has no items");
                 },
                 Err(e) => {
-                    tracing::warn!("Failed to parse collectible product for SKU {}: {}", sku_id, e);
+                    tracing::warn!("Failed to parse collectible product for SKU {}: {}", sku_id, e.without_url());
                 }
             }
         },
         Err(e) => {
-            tracing::warn!("Failed to fetch collectible product for SKU {}: {}", sku_id, e);
+            tracing::warn!("Failed to fetch collectible product for SKU {} from {}: {}", sku_id, url, e.without_url());
         }
     }
     None
@@ -0,0 +1,187 @@
+use crate::config::{AppConfig, Destination, DryRunOutput, MqttConfig};
+use crate::types::error::AppError;
+use crate::types::event::{QuestEvent, QuestEventKind};
+use crate::export;
+use crate::types::constants::COLOR_DEFAULT;
+use crate::types::digest::QuestDigest;
+use crate::communication::{json_webhook, ntfy, payload, telegram, text, webhook};
+use crate::communication::text::QuestText;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::io::Write;
+
+/// Render what would be sent to a destination, without sending it
+///
+/// The result names the destination and event and holds the request body (and the
+/// headers that matter) in the form the destination would receive it. Credentials
+/// are left out.
+///
+/// # Errors
+///
+/// Returns `AppError` if the Discord payload cannot be built or the event cannot be serialized
+pub async fn render(
+    client: &Client,
+    config: &AppConfig,
+    destination: &Destination,
+    event: &QuestEvent,
+) -> Result<Value, AppError> {
+    let request = match destination {
+        Destination::Discord(_) => webhook::build_quest_payload(client, config, event).await?,
+        Destination::Json(json_config) => {
+            let body = serde_json::to_vec(event)
+                .map_err(|e| AppError::Parse(format!("Failed to serialize quest event: {e}")))?;
+            let signature = json_config.secret.as_ref().map(|secret| json_webhook::sign_payload(secret.expose(), &body));
+            json!({
+                "headers": {
+                    json_webhook::EVENT_HEADER: event.event.as_str(),
+                    json_webhook::SIGNATURE_HEADER: signature,
+                },
+                "body": event,
+            })
+        }
+        Destination::Telegram(tgram) => json!({
+            "chat_ids": tgram.chat_ids,
+            "text": telegram::format_message(&text::format_quest_text(event)),
+            "parse_mode": "HTML",
+        }),
+        Destination::Ntfy(topic) => {
+            let text = text::format_quest_text(event);
+            json!({
+                "topic": topic.topic,
+                "headers": {
+                    "Title": ntfy::title_header(&text),
+                    "Tags": ntfy::NTFY_TAGS,
+                    "Click": event.computed.quest_url,
+                    "Attach": event.computed.banner_url,
+                    "Priority": topic.priority,
+                },
+                "body": text.body,
+            })
+        }
+        Destination::Email(mail) => {
+            let text = text::format_quest_text(event);
+            json!({
+                "from": mail.from,
+                "to": mail.to,
+                "subject": text.title,
+                "body": text.body,
+            })
+        }
+    };
+
+    Ok(envelope(&destination.label(), event, request))
+}
+
+/// Render the MQTT messages an event would publish
+#[must_use]
+pub fn render_mqtt(mqtt: &MqttConfig, event: &QuestEvent) -> Value {
+    let quest_topic = format!("{}/{}", mqtt.topic_prefix, event.quest.id);
+    // Expired quests clear their retained topic with an empty message
+    let retained = if event.event == QuestEventKind::Expired { Value::Null } else { json!(event) };
+    envelope("mqtt", event, json!([
+        { "topic": format!("{}/events", mqtt.topic_prefix), "retain": false, "payload": event },
+        { "topic": quest_topic, "retain": true, "payload": retained },
+    ]))
+}
+
+/// Render an operational alert to an ops destination
+#[must_use]
+pub fn render_alert(destination: &Destination, text: &QuestText) -> Value {
+    json!({
+        "destination": destination.label(),
+        "event": json_webhook::ALERT_EVENT,
+        "request": { "title": text.title, "body": text.body },
+    })
+}
+
+/// Render the digest of notifications held during a destination's quiet hours
+#[must_use]
+pub fn render_quiet_digest(destination: &Destination, text: &QuestText) -> Value {
+    json!({
+        "destination": destination.label(),
+        "event": "quiet_hours.digest",
+        "request": { "title": text.title, "body": text.body },
+    })
+}
+
+/// Render a scheduled quest digest to a digest destination
+///
+/// # Errors
+///
+/// Returns `AppError::Parse` if the digest cannot be serialized
+pub fn render_digest(destination: &Destination, digest: &QuestDigest) -> Result<Value, AppError> {
+    let request = match destination {
+        Destination::Discord(_) => payload::build_digest_payload(digest, COLOR_DEFAULT),
+        Destination::Json(_) => json_webhook::digest_body(digest)?,
+        _ => {
+            let text = text::format_digest(digest);
+            json!({ "title": text.title, "body": text.body })
+        }
+    };
+    Ok(json!({
+        "destination": destination.label(),
+        "event": json_webhook::DIGEST_EVENT,
+        "request": request,
+    }))
+}
+
+fn envelope(destination: &str, event: &QuestEvent, request: Value) -> Value {
+    json!({
+        "destination": destination,
+        "event": event.event.as_str(),
+        "quest_id": event.quest.id,
+        "request": request,
+    })
+}
+
+/// Write a rendered notification to stdout or to its own file in the output directory
+///
+/// # Errors
+///
+/// Returns `AppError::Io` if the output cannot be written
+pub async fn emit(output: &DryRunOutput, rendered: &Value) -> Result<(), AppError> {
+    let pretty = serde_json::to_string_pretty(rendered)
+        .map_err(|e| AppError::Parse(format!("Failed to serialize dry-run output: {e}")))?;
+
+    match output {
+        DryRunOutput::Stdout => {
+            let mut stdout = std::io::stdout().lock();
+            writeln!(stdout, "{pretty}")
+                .map_err(|e| AppError::Io(format!("Failed to write dry-run output: {e}")))?;
+        }
+        DryRunOutput::Dir(dir) => {
+            let path = dir.join(file_name(rendered));
+            tokio::fs::create_dir_all(dir).await
+                .map_err(|e| AppError::Io(format!("Failed to create '{}': {e}", dir.display())))?;
+            export::write_atomic_async(path.clone(), pretty).await?;
+            tracing::info!("Dry run: wrote {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// `<quest_id>-<event>-<destination>.json`, with characters unsafe in file names replaced
+fn file_name(rendered: &Value) -> String {
+    let name = ["quest_id", "event", "destination"]
+        .map(|key| rendered[key].as_str().unwrap_or_default())
+        .join("-");
+    let safe: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' })
+        .collect();
+    format!("{safe}.json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_name_is_safe_and_unique_per_destination() {
+        let rendered = json!({
+            "destination": "ntfy:ntfy.sh/quests",
+            "event": "quest.created",
+            "quest_id": "123",
+        });
+        assert_eq!(file_name(&rendered), "123-quest.created-ntfy_ntfy.sh_quests.json");
+    }
+}
@@ -0,0 +1,45 @@
+use crate::config::EmailDestination;
+use crate::types::error::AppError;
+use crate::types::secret::Secret;
+use crate::communication::text::QuestText;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+/// Send a quest message by email over SMTP
+///
+/// # Errors
+///
+/// Returns `AppError` if an address is invalid, the SMTP connection fails or the
+/// server rejects the message
+pub async fn send_email(destination: &EmailDestination, text: &QuestText) -> Result<(), AppError> {
+    let from: Mailbox = destination.from.parse()
+        .map_err(|e| AppError::Config(format!("Invalid email sender '{}': {e}", destination.from)))?;
+
+    let mut builder = Message::builder().from(from).subject(&text.title);
+    for to in &destination.to {
+        let mailbox: Mailbox = to.parse()
+            .map_err(|e| AppError::Config(format!("Invalid email recipient '{to}': {e}")))?;
+        builder = builder.to(mailbox);
+    }
+    let message = builder.body(text.body.clone())
+        .map_err(|e| AppError::Request(format!("Failed to build email: {e}")))?;
+
+    let transport_builder = if destination.implicit_tls {
+        AsyncSmtpTransport::<Tokio1Executor>::relay(&destination.host)
+    } else {
+        AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&destination.host)
+    }
+    .map_err(|e| AppError::Request(format!("Invalid SMTP host '{}': {e}", destination.host)))?;
+
+    let mut transport_builder = transport_builder.port(destination.port);
+    if let Some(username) = &destination.username {
+        let password = destination.password.as_ref().map(Secret::expose).unwrap_or_default().to_string();
+        transport_builder = transport_builder.credentials(Credentials::new(username.clone(), password));
+    }
+
+    transport_builder.build().send(message).await
+        .map_err(|e| AppError::Request(format!("SMTP delivery via {} failed: {e}", destination.host)))?;
+
+    Ok(())
+}
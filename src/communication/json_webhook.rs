@@ -0,0 +1,200 @@
+use crate::config::JsonWebhookConfig;
+use crate::types::error::AppError;
+use crate::types::digest::QuestDigest;
+use crate::types::event::QuestEvent;
+use crate::types::secret::{body_excerpt, Secret};
+use crate::metrics::metrics;
+use crate::communication::text::QuestText;
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use sha2::Sha256;
+use std::time::Duration;
+
+/// Header carrying the event name, e.g. `quest.created`
+pub const EVENT_HEADER: &str = "X-Quest-Event";
+/// Header carrying `sha256=<hex HMAC of the raw body>` when a secret is configured
+pub const SIGNATURE_HEADER: &str = "X-Quest-Signature";
+
+/// Event name of operational alerts, e.g. a rejected Discord token
+pub const ALERT_EVENT: &str = "ops.alert";
+
+/// Event name of scheduled quest digests
+pub const DIGEST_EVENT: &str = "quest.digest";
+
+const RETRY_BASE_DELAY_MS: u64 = 500;
+const REQUEST_TIMEOUT_SECS: u64 = 15;
+
+/// Compute the `X-Quest-Signature` value for a request body
+#[must_use]
+pub fn sign_payload(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// POST a quest event to the generic JSON webhook, retrying transient failures
+///
+/// # Errors
+///
+/// Returns `AppError` if:
+/// - The event cannot be serialized
+/// - Every attempt fails with a network error, 429 or 5xx status
+/// - The endpoint answers with any other non-success status
+pub async fn send_event(
+    client: &Client,
+    webhook: &JsonWebhookConfig,
+    event: &QuestEvent,
+) -> Result<(), AppError> {
+    let body = serde_json::to_vec(event)
+        .map_err(|e| AppError::Parse(format!("Failed to serialize quest event: {e}")))?;
+    post(client, webhook, event.event.as_str(), body).await
+}
+
+/// POST an operational alert as `{"event": "ops.alert", "title": ..., "message": ...}`
+///
+/// # Errors
+///
+/// Returns `AppError` for the same reasons as [`send_event`]
+pub async fn send_alert(client: &Client, webhook: &JsonWebhookConfig, text: &QuestText) -> Result<(), AppError> {
+    let body = serde_json::to_vec(&serde_json::json!({
+        "event": ALERT_EVENT,
+        "title": text.title,
+        "message": text.body,
+    }))
+    .map_err(|e| AppError::Parse(format!("Failed to serialize alert: {e}")))?;
+    post(client, webhook, ALERT_EVENT, body).await
+}
+
+/// POST a scheduled quest digest as `{"event": "quest.digest", ...}` with the digest's fields
+///
+/// # Errors
+///
+/// Returns `AppError` for the same reasons as [`send_event`]
+pub async fn send_digest(client: &Client, webhook: &JsonWebhookConfig, digest: &QuestDigest) -> Result<(), AppError> {
+    let body = serde_json::to_vec(&digest_body(digest)?)
+        .map_err(|e| AppError::Parse(format!("Failed to serialize digest: {e}")))?;
+    post(client, webhook, DIGEST_EVENT, body).await
+}
+
+/// The JSON body of a digest: its fields plus `"event": "quest.digest"`
+///
+/// # Errors
+///
+/// Returns `AppError::Parse` if the digest cannot be serialized
+pub fn digest_body(digest: &QuestDigest) -> Result<serde_json::Value, AppError> {
+    let mut body = serde_json::to_value(digest)
+        .map_err(|e| AppError::Parse(format!("Failed to serialize digest: {e}")))?;
+    body["event"] = serde_json::Value::from(DIGEST_EVENT);
+    Ok(body)
+}
+
+async fn post(client: &Client, webhook: &JsonWebhookConfig, event_name: &str, body: Vec<u8>) -> Result<(), AppError> {
+    let signature = webhook.secret.as_ref().map(|secret| sign_payload(secret.expose(), &body));
+
+    let mut attempt = 0;
+    loop {
+        let mut request = client.post(&webhook.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, event_name)
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .body(body.clone());
+        if let Some(signature) = &signature {
+            request = request.header(SIGNATURE_HEADER, signature);
+        }
+        if let Some(username) = &webhook.username {
+            request = request.basic_auth(username, webhook.password.as_ref().map(Secret::expose));
+        }
+
+        let mut rate_limited = false;
+        let error = match request.send().await {
+            Ok(res) if res.status().is_success() => return Ok(()),
+            Ok(res) => {
+                let status = res.status();
+                let body = error_body(&res.text().await.unwrap_or_default(), webhook);
+                let error = AppError::Request(format!("JSON webhook failed: {status} - {body}"));
+                rate_limited = status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+                if !status.is_server_error() && !rate_limited {
+                    return Err(error);
+                }
+                error
+            }
+            // The URL may embed a token, so only the error kind is reported
+            Err(e) => AppError::Request(format!("JSON webhook request failed: {}", e.without_url())),
+        };
+
+        if attempt >= webhook.max_retries {
+            return Err(error);
+        }
+
+        if rate_limited {
+            metrics().rate_limit_waits.with_label_values(&["json_webhook"]).inc();
+        }
+        let delay = Duration::from_millis(RETRY_BASE_DELAY_MS << attempt);
+        attempt += 1;
+        tracing::warn!(
+            "JSON webhook delivery of {} failed ({}), retry {}/{} in {:?}",
+            event_name, error, attempt, webhook.max_retries, delay
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Shorten a response body for an error message, removing the URL and credentials
+fn error_body(body: &str, webhook: &JsonWebhookConfig) -> String {
+    let body = [&webhook.secret, &webhook.password]
+        .into_iter()
+        .flatten()
+        .fold(body.to_string(), |body, secret| secret.scrub(&body));
+    body_excerpt(&body, &Secret::new(webhook.url.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn webhook(url: String) -> JsonWebhookConfig {
+        JsonWebhookConfig {
+            url,
+            secret: Some(Secret::new("signing-key")),
+            max_retries: 0,
+            username: None,
+            password: Some(Secret::new("hunter2")),
+        }
+    }
+
+    #[test]
+    fn sign_payload_matches_rfc4231_vector() {
+        assert_eq!(
+            sign_payload("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn sign_payload_depends_on_secret_and_body() {
+        let signature = sign_payload("secret", b"{}");
+        assert_ne!(signature, sign_payload("other", b"{}"));
+        assert_ne!(signature, sign_payload("secret", b"{ }"));
+    }
+
+    #[test]
+    fn error_bodies_hide_the_url_and_credentials() {
+        let webhook = webhook("https://hooks.example.com/t/abc123".to_string());
+        let body = error_body("bad request to https://hooks.example.com/t/abc123 signed with signing-key", &webhook);
+        assert_eq!(body, "bad request to [REDACTED] signed with [REDACTED]");
+    }
+
+    #[tokio::test]
+    async fn request_errors_hide_the_url() {
+        // A port nothing listens on, so the connection is refused
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let webhook = webhook(format!("http://127.0.0.1:{port}/t/abc123"));
+        let text = QuestText { title: "Title".to_string(), body: "Body".to_string() };
+
+        let Err(AppError::Request(message)) = send_alert(&Client::new(), &webhook, &text).await else {
+            panic!("expected a request error");
+        };
+        assert!(!message.contains("abc123"), "{message}");
+    }
+}
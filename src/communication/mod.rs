@@ -1,6 +1,14 @@
 pub mod scraper;
 pub mod webhook;
+pub mod json_webhook;
+pub mod telegram;
+pub mod ntfy;
+pub mod email;
+pub mod mqtt;
+pub mod notifier;
+pub mod dry_run;
 pub mod collectibles;
 pub mod payload;
 pub mod reward;
 pub mod task;
+pub mod text;
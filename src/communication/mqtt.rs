@@ -0,0 +1,228 @@
+use crate::config::MqttConfig;
+use crate::types::error::AppError;
+use crate::types::event::{QuestEvent, QuestEventKind};
+use crate::types::secret::Secret;
+use crate::types::state::QuestState;
+use rumqttc::{AsyncClient, Event, Incoming, LastWill, MqttOptions, Outgoing, QoS, Transport};
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+const KEEP_ALIVE_SECS: u64 = 30;
+const RECONNECT_DELAY_SECS: u64 = 5;
+const REQUEST_CHANNEL_CAPACITY: usize = 64;
+const DISCONNECT_TIMEOUT_SECS: u64 = 5;
+const STATUS_ONLINE: &str = "online";
+const STATUS_OFFLINE: &str = "offline";
+
+/// Publishes quest events to an MQTT broker
+///
+/// Topics, relative to the configured prefix:
+/// - `<prefix>/<quest_id>`: retained latest event per active quest, cleared on expiry
+/// - `<prefix>/events`: every event, not retained
+/// - `<prefix>/status`: retained `online`/`offline` availability (last will)
+///
+/// Publishing never waits: if the client queue is full because the broker is
+/// unreachable, the message is dropped with an error instead of stalling the poll loop.
+pub struct MqttPublisher {
+    client: AsyncClient,
+    topic_prefix: String,
+    qos: QoS,
+    /// Quest IDs with a retained topic on the broker, learned from the `<prefix>/+`
+    /// subscription, so topics left behind by a previous run can be cleared
+    retained_ids: Arc<Mutex<BTreeSet<String>>>,
+    event_loop: JoinHandle<()>,
+}
+
+impl MqttPublisher {
+    /// Create the client and spawn its event loop, which reconnects on its own
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError` if the CA file configured for TLS cannot be read
+    pub fn connect(config: &MqttConfig) -> Result<Self, AppError> {
+        let qos = qos_from_level(config.qos);
+        let status_topic = format!("{}/status", config.topic_prefix);
+
+        let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+        options.set_keep_alive(Duration::from_secs(KEEP_ALIVE_SECS));
+        options.set_last_will(LastWill::new(&status_topic, STATUS_OFFLINE, qos, true));
+        if let Some(username) = &config.username {
+            options.set_credentials(username, config.password.as_ref().map(Secret::expose).unwrap_or_default());
+        }
+        if config.tls {
+            let transport = match &config.ca_file {
+                Some(path) => {
+                    let ca = std::fs::read(path)
+                        .map_err(|e| AppError::Config(format!("Failed to read MQTT_CA_FILE '{path}': {e}")))?;
+                    Transport::tls(ca, None, None)
+                }
+                None => Transport::tls_with_default_config(),
+            };
+            options.set_transport(transport);
+        }
+
+        let (client, mut eventloop) = AsyncClient::new(options, REQUEST_CHANNEL_CAPACITY);
+        let loop_client = client.clone();
+        let broker = format!("{}:{}", config.host, config.port);
+        let prefix = config.topic_prefix.clone();
+        let retained_ids = Arc::new(Mutex::new(BTreeSet::new()));
+        let loop_retained_ids = Arc::clone(&retained_ids);
+
+        // The event loop is the only consumer of the request queue, so it must never
+        // wait on it: every request issued from here uses the non-blocking `try_*` API
+        let event_loop = tokio::spawn(async move {
+            loop {
+                match eventloop.poll().await {
+                    Ok(Event::Incoming(Incoming::ConnAck(_))) => {
+                        tracing::info!("Connected to MQTT broker {}", broker);
+                        if let Err(e) = loop_client.try_publish(&status_topic, qos, true, STATUS_ONLINE) {
+                            tracing::warn!("Failed to publish MQTT status: {}", e);
+                        }
+                        if let Err(e) = loop_client.try_subscribe(format!("{prefix}/+"), QoS::AtMostOnce) {
+                            tracing::warn!("Failed to subscribe to retained MQTT quest topics: {}", e);
+                        }
+                    }
+                    Ok(Event::Incoming(Incoming::Publish(publish))) => {
+                        if let Some(id) = retained_quest_id(&prefix, &publish.topic, publish.retain, &publish.payload) {
+                            if let Ok(mut ids) = loop_retained_ids.lock() {
+                                ids.insert(id.to_string());
+                            }
+                        }
+                    }
+                    Ok(Event::Outgoing(Outgoing::Disconnect)) => return,
+                    Ok(_) => {}
+                    Err(e) => {
+                        tracing::warn!("MQTT connection to {} failed: {}. Retrying in {}s", broker, e, RECONNECT_DELAY_SECS);
+                        tokio::time::sleep(Duration::from_secs(RECONNECT_DELAY_SECS)).await;
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            client,
+            topic_prefix: config.topic_prefix.clone(),
+            qos,
+            retained_ids,
+            event_loop,
+        })
+    }
+
+    /// Flush queued messages and disconnect cleanly, so the broker does not publish the
+    /// `offline` last will. Gives up after a few seconds if the broker is unreachable.
+    pub async fn disconnect(self) {
+        if self.client.try_disconnect().is_ok() {
+            let abort = self.event_loop.abort_handle();
+            if tokio::time::timeout(Duration::from_secs(DISCONNECT_TIMEOUT_SECS), self.event_loop).await.is_err() {
+                tracing::warn!("Timed out disconnecting from the MQTT broker");
+                abort.abort();
+            }
+        } else {
+            self.event_loop.abort();
+        }
+    }
+
+    /// Publish an event to the events topic and update the quest's retained topic
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError` if the event cannot be serialized or the client queue is full or closed
+    pub fn publish_event(&self, event: &QuestEvent) -> Result<(), AppError> {
+        let payload = serialize_event(event)?;
+        self.publish_retained(event, &payload)?;
+
+        let events_topic = format!("{}/events", self.topic_prefix);
+        self.client.try_publish(events_topic, self.qos, false, payload)
+            .map_err(|e| AppError::Request(format!("MQTT publish failed: {e}")))
+    }
+
+    /// Update only the quest's retained topic, without announcing the event.
+    /// Used when notifications are suppressed so subscribers still see current quests.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError` if the event cannot be serialized or the client queue is full or closed
+    pub fn publish_state(&self, event: &QuestEvent) -> Result<(), AppError> {
+        self.publish_retained(event, &serialize_event(event)?)
+    }
+
+    /// Clear retained quest topics left on the broker for quests that are not active in `state`,
+    /// e.g. quests that expired while the monitor was stopped
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError` if the client queue is full or closed
+    pub fn clear_stale(&self, state: &QuestState) -> Result<(), AppError> {
+        let stale: Vec<String> = match self.retained_ids.lock() {
+            Ok(ids) => ids.iter()
+                .filter(|id| state.quests.get(id.as_str()).is_none_or(|tracked| tracked.expired))
+                .cloned()
+                .collect(),
+            Err(_) => return Ok(()),
+        };
+
+        for id in stale {
+            tracing::info!("Clearing stale retained MQTT topic for quest {}", id);
+            self.clear_retained(&id)?;
+        }
+        Ok(())
+    }
+
+    fn publish_retained(&self, event: &QuestEvent, payload: &[u8]) -> Result<(), AppError> {
+        if event.event == QuestEventKind::Expired {
+            return self.clear_retained(&event.quest.id);
+        }
+        let quest_topic = format!("{}/{}", self.topic_prefix, event.quest.id);
+        self.client.try_publish(quest_topic, self.qos, true, payload.to_vec())
+            .map_err(|e| AppError::Request(format!("MQTT publish failed: {e}")))
+    }
+
+    /// An empty retained message removes the quest from subscribers' state
+    fn clear_retained(&self, id: &str) -> Result<(), AppError> {
+        let quest_topic = format!("{}/{}", self.topic_prefix, id);
+        self.client.try_publish(quest_topic, self.qos, true, Vec::new())
+            .map_err(|e| AppError::Request(format!("MQTT publish failed: {e}")))?;
+        if let Ok(mut ids) = self.retained_ids.lock() {
+            ids.remove(id);
+        }
+        Ok(())
+    }
+}
+
+fn serialize_event(event: &QuestEvent) -> Result<Vec<u8>, AppError> {
+    serde_json::to_vec(event).map_err(|e| AppError::Parse(format!("Failed to serialize quest event: {e}")))
+}
+
+/// Quest ID of a non-empty retained message on `<prefix>/<quest_id>`
+fn retained_quest_id<'a>(prefix: &str, topic: &'a str, retain: bool, payload: &[u8]) -> Option<&'a str> {
+    if !retain || payload.is_empty() {
+        return None;
+    }
+    topic.strip_prefix(prefix)?
+        .strip_prefix('/')
+        .filter(|id| !matches!(*id, "status" | "events"))
+}
+
+const fn qos_from_level(level: u8) -> QoS {
+    match level {
+        0 => QoS::AtMostOnce,
+        2 => QoS::ExactlyOnce,
+        _ => QoS::AtLeastOnce,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retained_quest_id_only_matches_non_empty_retained_quest_topics() {
+        assert_eq!(retained_quest_id("discord/quests", "discord/quests/123", true, b"{}"), Some("123"));
+        assert_eq!(retained_quest_id("discord/quests", "discord/quests/123", false, b"{}"), None);
+        assert_eq!(retained_quest_id("discord/quests", "discord/quests/123", true, b""), None);
+        assert_eq!(retained_quest_id("discord/quests", "discord/quests/status", true, b"online"), None);
+        assert_eq!(retained_quest_id("discord/quests", "discord/questsx/1", true, b"{}"), None);
+    }
+}
@@ -0,0 +1,257 @@
+use crate::config::{AppConfig, Destination, DryRunOutput, Route};
+use crate::types::error::AppError;
+use crate::types::digest::QuestDigest;
+use crate::types::event::QuestEvent;
+use crate::types::state::QuestState;
+use crate::metrics::metrics;
+use crate::communication::{dry_run, email, json_webhook, mqtt::MqttPublisher, ntfy, telegram, text, webhook};
+use crate::communication::text::QuestText;
+use reqwest::Client;
+use tracing::Instrument;
+
+/// Outcome of delivering one event to every interested destination
+#[derive(Default)]
+pub struct DeliveryReport {
+    /// Label of each destination that received the event
+    pub delivered: Vec<String>,
+    /// Label and error of each destination that failed
+    pub failed: Vec<(String, AppError)>,
+    /// Label of each destination in its quiet hours, which should receive the event later
+    pub held: Vec<String>,
+}
+
+/// Fans quest events out to every configured destination
+pub struct Notifier {
+    client: Client,
+    mqtt: Option<MqttPublisher>,
+    /// Render notifications here instead of sending them
+    dry_run: Option<DryRunOutput>,
+}
+
+impl Notifier {
+    /// Create the notifier, connecting to long-lived destinations such as MQTT
+    ///
+    /// In dry-run mode nothing is connected and every notification is rendered to
+    /// the dry-run output instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError` if a destination cannot be initialised
+    pub fn new(config: &AppConfig) -> Result<Self, AppError> {
+        if config.dry_run.is_some() {
+            return Ok(Self { client: Client::new(), mqtt: None, dry_run: config.dry_run.clone() });
+        }
+        let mqtt = config.mqtt.as_ref().map(MqttPublisher::connect).transpose()?;
+        Ok(Self { client: Client::new(), mqtt, dry_run: None })
+    }
+
+    /// Create a notifier for one-off commands, without connecting to MQTT
+    #[must_use]
+    pub fn without_mqtt() -> Self {
+        Self { client: Client::new(), mqtt: None, dry_run: None }
+    }
+
+    /// Deliver an event to all destinations interested in it
+    ///
+    /// Each destination's filters decide whether it receives the event (see
+    /// [`crate::config::Route::accepts`]); MQTT receives every event. All destinations
+    /// are attempted even if one fails, and failures are returned so they can be retried.
+    /// Destinations in their quiet hours are skipped and returned as held.
+    pub async fn notify(&self, config: &AppConfig, event: &QuestEvent) -> DeliveryReport {
+        let mut report = DeliveryReport::default();
+        let now = chrono::Utc::now();
+
+        for route in &config.destinations {
+            if !route.accepts(event) {
+                continue;
+            }
+            if route.quiet_hours.as_ref().is_some_and(|quiet| quiet.contains(now)) {
+                report.held.push(route.label());
+                continue;
+            }
+            match self.deliver(config, route, event).await {
+                Ok(()) => report.delivered.push(route.label()),
+                Err(e) => report.failed.push((route.label(), e)),
+            }
+        }
+
+        if let (Some(output), Some(mqtt)) = (&self.dry_run, &config.mqtt) {
+            if let Err(e) = dry_run::emit(output, &dry_run::render_mqtt(mqtt, event)).await {
+                tracing::error!("Failed to write dry-run MQTT event for quest {}: {}", event.quest.id, e);
+            }
+        } else if let Some(mqtt) = &self.mqtt {
+            // MQTT keeps its own queue, so failures are only logged
+            if let Err(e) = mqtt.publish_event(event) {
+                tracing::error!(
+                    "Failed to publish {} MQTT event for quest {}: {}",
+                    event.event.as_str(), event.quest.id, e
+                );
+            }
+        }
+
+        report
+    }
+
+    /// Close long-lived connections, flushing queued MQTT messages
+    pub async fn shutdown(self) {
+        if let Some(mqtt) = self.mqtt {
+            mqtt.disconnect().await;
+        }
+    }
+
+    /// Mirror events on the retained MQTT quest topics without announcing them anywhere.
+    /// Used on the initial poll, when notifications are suppressed.
+    pub fn publish_state(&self, events: &[QuestEvent]) {
+        let Some(mqtt) = &self.mqtt else { return };
+        for event in events {
+            if let Err(e) = mqtt.publish_state(event) {
+                tracing::error!("Failed to publish MQTT state for quest {}: {}", event.quest.id, e);
+            }
+        }
+    }
+
+    /// Clear retained MQTT quest topics for quests no longer active in `state`
+    pub fn clear_stale_state(&self, state: &QuestState) {
+        let Some(mqtt) = &self.mqtt else { return };
+        if let Err(e) = mqtt.clear_stale(state) {
+            tracing::error!("Failed to clear stale MQTT quest topics: {}", e);
+        }
+    }
+
+    /// Deliver an event to every configured destination regardless of its kind,
+    /// returning each destination label with its result
+    pub async fn send_to_all(&self, config: &AppConfig, event: &QuestEvent) -> Vec<(String, Result<(), AppError>)> {
+        let mut results = Vec::with_capacity(config.destinations.len());
+        for route in &config.destinations {
+            results.push((route.label(), self.deliver(config, route, event).await));
+        }
+        results
+    }
+
+    /// Deliver an event to a single destination, logging a failure
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError` if the destination rejected the event or could not be reached
+    pub async fn deliver(&self, config: &AppConfig, route: &Route, event: &QuestEvent) -> Result<(), AppError> {
+        let label = route.label();
+        let span = tracing::info_span!(
+            "deliver", quest_id = %event.quest.id, event = event.event.as_str(), destination = %label
+        );
+        let result = self.send(config, &route.destination, event).instrument(span.clone()).await;
+        let outcome = if result.is_ok() { "sent" } else { "failed" };
+        metrics().notifications.with_label_values(&[&label, outcome]).inc();
+        span.in_scope(|| match &result {
+            Ok(()) => tracing::debug!(status = outcome, "Delivered {} for quest {} to {}", event.event.as_str(), event.quest.id, label),
+            Err(e) => tracing::error!(
+                status = outcome,
+                "Failed to deliver {} for quest {} to {}: {}",
+                event.event.as_str(), event.quest.id, label, e
+            ),
+        });
+        result
+    }
+
+    /// Deliver the notifications held during a destination's quiet hours as one message
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError` if the message cannot be sent
+    pub async fn deliver_quiet_digest(&self, route: &Route, events: &[QuestEvent]) -> Result<(), AppError> {
+        let label = route.label();
+        let digest = text::format_quiet_digest(events);
+        let result = match &self.dry_run {
+            Some(output) => dry_run::emit(output, &dry_run::render_quiet_digest(&route.destination, &digest)).await,
+            None => self.send_text(&route.destination, &digest).await,
+        };
+        let outcome = if result.is_ok() { "sent" } else { "failed" };
+        metrics().notifications.with_label_values(&[&label, outcome]).inc();
+        if let Err(e) = &result {
+            tracing::error!(status = outcome, "Failed to deliver the quiet hours digest of {} notifications to {}: {}", events.len(), label, e);
+        }
+        result
+    }
+
+    /// Send a scheduled quest digest to every `DIGEST_URLS` destination. Failures are
+    /// logged and counted; the digest is not retried.
+    pub async fn send_digest(&self, destinations: &[Destination], digest: &QuestDigest) {
+        for destination in destinations {
+            let label = destination.label();
+            let result = match &self.dry_run {
+                Some(output) => match dry_run::render_digest(destination, digest) {
+                    Ok(rendered) => dry_run::emit(output, &rendered).await,
+                    Err(e) => Err(e),
+                },
+                None => self.send_digest_to(destination, digest).await,
+            };
+            let outcome = if result.is_ok() { "sent" } else { "failed" };
+            metrics().notifications.with_label_values(&[&label, outcome]).inc();
+            match result {
+                Ok(()) => tracing::info!("Sent the quest digest ({} active quests) to {}", digest.active, label),
+                Err(e) => tracing::error!(status = outcome, "Failed to send the quest digest to {}: {}", label, e),
+            }
+        }
+    }
+
+    async fn send_digest_to(&self, destination: &Destination, digest: &QuestDigest) -> Result<(), AppError> {
+        match destination {
+            Destination::Discord(discord) => webhook::send_digest(&self.client, &discord.webhook_url, digest).await,
+            Destination::Json(json) => json_webhook::send_digest(&self.client, json, digest).await,
+            Destination::Telegram(tgram) => telegram::send_message(&self.client, tgram, &text::format_digest(digest)).await,
+            Destination::Ntfy(topic) => ntfy::publish_digest(&self.client, topic, &text::format_digest(digest)).await,
+            Destination::Email(mail) => email::send_email(mail, &text::format_digest(digest)).await,
+        }
+    }
+
+    /// Send an operational alert to every `OPS_NOTIFY_URLS` destination. Failures are
+    /// logged; without ops destinations the alert is only logged.
+    pub async fn alert(&self, config: &AppConfig, text: &QuestText) {
+        if config.ops_destinations.is_empty() {
+            tracing::warn!("No OPS_NOTIFY_URLS configured for alert: {}", text.title);
+            return;
+        }
+
+        for destination in &config.ops_destinations {
+            let result = match &self.dry_run {
+                Some(output) => dry_run::emit(output, &dry_run::render_alert(destination, text)).await,
+                None => self.send_text(destination, text).await,
+            };
+            match result {
+                Ok(()) => tracing::info!("Sent alert '{}' to {}", text.title, destination.label()),
+                Err(e) => tracing::error!("Failed to send alert '{}' to {}: {}", text.title, destination.label(), e),
+            }
+        }
+    }
+
+    /// Send a plain-text message; JSON webhooks receive it as an `ops.alert` event
+    async fn send_text(&self, destination: &Destination, text: &QuestText) -> Result<(), AppError> {
+        match destination {
+            Destination::Discord(discord) => webhook::send_text(&self.client, &discord.webhook_url, text).await,
+            Destination::Json(json) => json_webhook::send_alert(&self.client, json, text).await,
+            Destination::Telegram(tgram) => telegram::send_message(&self.client, tgram, text).await,
+            Destination::Ntfy(topic) => ntfy::publish_alert(&self.client, topic, text).await,
+            Destination::Email(mail) => email::send_email(mail, text).await,
+        }
+    }
+
+    async fn send(&self, config: &AppConfig, destination: &Destination, event: &QuestEvent) -> Result<(), AppError> {
+        if let Some(output) = &self.dry_run {
+            let rendered = dry_run::render(&self.client, config, destination, event).await?;
+            return dry_run::emit(output, &rendered).await;
+        }
+
+        match destination {
+            Destination::Discord(discord) => {
+                webhook::send_webhook(&self.client, config, &discord.webhook_url, event).await
+            }
+            Destination::Json(json) => json_webhook::send_event(&self.client, json, event).await,
+            Destination::Telegram(tgram) => {
+                telegram::send_message(&self.client, tgram, &text::format_quest_text(event)).await
+            }
+            Destination::Ntfy(topic) => {
+                ntfy::publish(&self.client, topic, event, &text::format_quest_text(event)).await
+            }
+            Destination::Email(mail) => email::send_email(mail, &text::format_quest_text(event)).await,
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use crate::config::NtfyDestination;
+use crate::types::error::AppError;
+use crate::types::event::QuestEvent;
+use crate::types::secret::{body_excerpt, Secret};
+use crate::communication::text::QuestText;
+use reqwest::Client;
+
+pub const NTFY_TAGS: &str = "video_game";
+const ALERT_TAGS: &str = "warning";
+
+/// Publish a quest message to an ntfy topic
+///
+/// # Errors
+///
+/// Returns `AppError` if the request fails or the server returns a non-success status
+pub async fn publish(
+    client: &Client,
+    destination: &NtfyDestination,
+    event: &QuestEvent,
+    text: &QuestText,
+) -> Result<(), AppError> {
+    let request = request(client, destination, text, NTFY_TAGS)
+        .header("Click", &event.computed.quest_url)
+        .header("Attach", &event.computed.banner_url);
+    send(request, destination).await
+}
+
+/// Publish an operational alert to an ntfy topic
+///
+/// # Errors
+///
+/// Returns `AppError` if the request fails or the server returns a non-success status
+pub async fn publish_alert(client: &Client, destination: &NtfyDestination, text: &QuestText) -> Result<(), AppError> {
+    send(request(client, destination, text, ALERT_TAGS), destination).await
+}
+
+/// Publish a scheduled quest digest to an ntfy topic
+///
+/// # Errors
+///
+/// Returns `AppError` if the request fails or the server returns a non-success status
+pub async fn publish_digest(client: &Client, destination: &NtfyDestination, text: &QuestText) -> Result<(), AppError> {
+    send(request(client, destination, text, NTFY_TAGS), destination).await
+}
+
+fn request(client: &Client, destination: &NtfyDestination, text: &QuestText, tags: &str) -> reqwest::RequestBuilder {
+    let url = format!("{}/{}", destination.server_url, destination.topic);
+
+    let mut request = client.post(&url)
+        .header("Title", title_header(text))
+        .header("Tags", tags)
+        .body(text.body.clone());
+
+    if let Some(priority) = destination.priority {
+        request = request.header("Priority", priority.to_string());
+    }
+    if let Some(token) = &destination.access_token {
+        request = request.bearer_auth(token.expose());
+    } else if let Some(username) = &destination.username {
+        request = request.basic_auth(username, destination.password.as_ref().map(Secret::expose));
+    }
+    request
+}
+
+async fn send(request: reqwest::RequestBuilder, destination: &NtfyDestination) -> Result<(), AppError> {
+    let res = request.send().await
+        // Anyone who knows the topic URL can read it, so only the error kind is reported
+        .map_err(|e| AppError::Request(format!("ntfy request failed: {}", e.without_url())))?;
+
+    if !res.status().is_success() {
+        let status = res.status();
+        let body = error_body(&res.text().await.unwrap_or_default(), destination);
+        return Err(AppError::Request(format!("ntfy publish failed: {status} - {body}")));
+    }
+
+    Ok(())
+}
+
+/// Shorten a response body for an error message, removing the topic and credentials
+fn error_body(body: &str, destination: &NtfyDestination) -> String {
+    let body = [&destination.access_token, &destination.password]
+        .into_iter()
+        .flatten()
+        .fold(body.to_string(), |body, secret| secret.scrub(&body));
+    body_excerpt(&body, &Secret::new(destination.topic.as_str()))
+}
+
+/// Value of the `Title` header. Header values must be ASCII; non-ASCII titles are
+/// carried in the body instead.
+#[must_use]
+pub fn title_header(text: &QuestText) -> &str {
+    if text.title.is_ascii() { text.title.as_str() } else { "New Discord Quest" }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn destination(server_url: String) -> NtfyDestination {
+        NtfyDestination {
+            server_url,
+            topic: "secret-quest-topic".to_string(),
+            username: Some("bot".to_string()),
+            password: Some(Secret::new("hunter2")),
+            access_token: None,
+            priority: None,
+        }
+    }
+
+    #[test]
+    fn error_bodies_hide_the_topic_and_credentials() {
+        let body = error_body("denied: secret-quest-topic (bot:hunter2)", &destination(String::new()));
+        assert_eq!(body, "denied: [REDACTED] (bot:[REDACTED])");
+    }
+
+    #[tokio::test]
+    async fn request_errors_hide_the_topic_url() {
+        // A port nothing listens on, so the connection is refused
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let destination = destination(format!("http://127.0.0.1:{port}"));
+        let text = QuestText { title: "Title".to_string(), body: "Body".to_string() };
+
+        let Err(AppError::Request(message)) = publish_alert(&Client::new(), &destination, &text).await else {
+            panic!("expected a request error");
+        };
+        assert!(!message.contains("secret-quest-topic"), "{message}");
+    }
+}
@@ -1,7 +1,11 @@
+use crate::types::digest::{DigestQuest, QuestDigest};
 use serde_json::json;
+use std::fmt::Write as FmtWrite;
 
 /// Data structure for building Discord webhook payload
 pub struct WebhookPayloadData<'a> {
+    /// Title for the event kind, e.g. `New Quest` or `Quest Expired`
+    pub heading: &'a str,
     pub accent_color: i32,
     pub game_title: &'a str,
     pub game_publisher: &'a str,
@@ -13,6 +17,8 @@ pub struct WebhookPayloadData<'a> {
     pub app_link: &'a str,
     pub app_id: &'a str,
     pub features_string: String,
+    /// Extra `**Visible to**` and `**Progress**` lines, each starting with a newline
+    pub account_info: String,
     pub tasks_string: String,
     pub reward_icon_url: String,
     pub reward_info: String,
@@ -30,7 +36,7 @@ pub fn build_webhook_payload(data: &WebhookPayloadData) -> serde_json::Value {
                 "components": [
                     {
                         "type": 10,
-                        "content": format!("## **New Quest** - [{}]({})", data.game_title, data.cta_link)
+                        "content": format!("## **{}** - [{}]({})", data.heading, data.game_title, data.cta_link)
                     },
                     {
                         "type": 12,
@@ -52,7 +58,7 @@ pub fn build_webhook_payload(data: &WebhookPayloadData) -> serde_json::Value {
                     {
                         "type": 10,
                         "content": format!(
-                            "\n# Quest Info\n**Duration**: <t:{}:d> - <t:{}:d>\n**Reedemable Platforms**: Cross Platform\n**Game**: {} ({})\n**Application**: [{}]({}) (``{}``)\n**Features**: {}",
+                            "\n# Quest Info\n**Duration**: <t:{}:d> - <t:{}:d>\n**Reedemable Platforms**: Cross Platform\n**Game**: {} ({})\n**Application**: [{}]({}) (``{}``)\n**Features**: {}{}",
                             data.start_timestamp,
                             data.expires_timestamp,
                             data.game_title,
@@ -60,7 +66,8 @@ pub fn build_webhook_payload(data: &WebhookPayloadData) -> serde_json::Value {
                             data.app_name,
                             data.app_link,
                             data.app_id,
-                            data.features_string
+                            data.features_string,
+                            data.account_info
                         )
                     },
                     {
@@ -109,3 +116,168 @@ pub fn build_webhook_payload(data: &WebhookPayloadData) -> serde_json::Value {
         "flags": 32768
     })
 }
+
+/// Most quests listed in each section of a digest
+const MAX_DIGEST_QUESTS: usize = 10;
+/// Longest value Discord accepts in an embed field
+const MAX_EMBED_FIELD_LEN: usize = 1024;
+
+/// Build the Components V2 webhook body for a scheduled quest digest
+#[must_use]
+pub fn build_digest_payload(digest: &QuestDigest, accent_color: i32) -> serde_json::Value {
+    let divider = json!({ "type": 14, "divider": true, "spacing": 1 });
+    json!({
+        "components": [
+            {
+                "type": 17,
+                "accent_color": accent_color,
+                "components": [
+                    {
+                        "type": 10,
+                        "content": format!("## **Quest Digest**\n{}", digest_summary(digest))
+                    },
+                    divider,
+                    {
+                        "type": 10,
+                        "content": format!("# Ending Soon\n{}", digest_ending_soon(digest))
+                    },
+                    divider,
+                    {
+                        "type": 10,
+                        "content": format!("# New Quests\n{}", digest_new_quests(digest))
+                    },
+                    divider,
+                    {
+                        "type": 10,
+                        "content": format!("# By Reward Type\n{}", digest_reward_types(digest))
+                    },
+                    divider,
+                    {
+                        "type": 10,
+                        "content": format!("New quests since <t:{}:f>", digest.period_start.timestamp())
+                    }
+                ]
+            }
+        ],
+        "flags": 32768
+    })
+}
+
+/// Build a plain embed body for a scheduled quest digest, for webhooks that reject
+/// Components V2 messages
+#[must_use]
+pub fn build_digest_embed(digest: &QuestDigest, color: i32) -> serde_json::Value {
+    let field = |name: &str, value: String| json!({ "name": name, "value": truncate(value, MAX_EMBED_FIELD_LEN) });
+    json!({
+        "embeds": [
+            {
+                "title": "Quest Digest",
+                "color": color,
+                "description": digest_summary(digest),
+                "fields": [
+                    field("Ending Soon", digest_ending_soon(digest)),
+                    field("New Quests", digest_new_quests(digest)),
+                    field("By Reward Type", digest_reward_types(digest)),
+                ],
+                "footer": { "text": "New quests since" },
+                "timestamp": digest.period_start.to_rfc3339(),
+            }
+        ]
+    })
+}
+
+fn digest_summary(digest: &QuestDigest) -> String {
+    format!("**Active quests**: {}\n**Orbs available**: {}", digest.active, digest.total_orbs)
+}
+
+fn digest_ending_soon(digest: &QuestDigest) -> String {
+    if digest.ending_soon.is_empty() {
+        return format!("No quest ends in the next {} hours", digest.ending_hours);
+    }
+    quest_lines(&digest.ending_soon, |quest| {
+        quest.expires_at_unix.map(|ts| format!(" - ends <t:{ts}:R>")).unwrap_or_default()
+    })
+}
+
+fn digest_new_quests(digest: &QuestDigest) -> String {
+    if digest.new_quests.is_empty() {
+        return "No new quests".to_string();
+    }
+    quest_lines(&digest.new_quests, |quest| {
+        quest.reward_name.as_ref().map(|name| format!(" - {name}")).unwrap_or_default()
+    })
+}
+
+fn digest_reward_types(digest: &QuestDigest) -> String {
+    if digest.reward_types.is_empty() {
+        return "No active quests".to_string();
+    }
+    let mut lines = String::new();
+    for (reward_type, quests) in &digest.reward_types {
+        let orbs: i64 = quests.iter().map(|quest| i64::from(quest.orb_quantity.unwrap_or(0))).sum();
+        let orbs = if orbs > 0 { format!(", {orbs} orbs") } else { String::new() };
+        let titles: Vec<String> = quests.iter()
+            .take(MAX_DIGEST_QUESTS)
+            .map(|quest| format!("[{}]({})", quest.game_title, quest.quest_url))
+            .collect();
+        let more = quests.len().saturating_sub(MAX_DIGEST_QUESTS);
+        let more = if more > 0 { format!(" and {more} more") } else { String::new() };
+        let _ = writeln!(lines, "**{reward_type}** ({}{orbs}): {}{more}", quests.len(), titles.join(", "));
+    }
+    lines.trim_end().to_string()
+}
+
+/// One `- [Game](url)` line per quest, followed by `detail`
+fn quest_lines(quests: &[DigestQuest], detail: impl Fn(&DigestQuest) -> String) -> String {
+    let mut lines = String::new();
+    for quest in quests.iter().take(MAX_DIGEST_QUESTS) {
+        let _ = writeln!(lines, "- [{}]({}){}", quest.game_title, quest.quest_url, detail(quest));
+    }
+    if quests.len() > MAX_DIGEST_QUESTS {
+        let _ = write!(lines, "...and {} more", quests.len() - MAX_DIGEST_QUESTS);
+    }
+    lines.trim_end().to_string()
+}
+
+/// Cut `value` to at most `max` characters, ending it with an ellipsis when shortened
+fn truncate(value: String, max: usize) -> String {
+    if value.chars().count() <= max {
+        return value;
+    }
+    let mut cut: String = value.chars().take(max - 1).collect();
+    cut.push('…');
+    cut
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn webhook_title_names_the_event_kind() {
+        let data = WebhookPayloadData {
+            heading: "Quest Expired",
+            accent_color: 0,
+            game_title: "Game",
+            game_publisher: "Publisher",
+            cta_link: "https://discord.com/quests/1",
+            banner_url: String::new(),
+            start_timestamp: 0,
+            expires_timestamp: 0,
+            app_name: "App",
+            app_link: "",
+            app_id: "1",
+            features_string: String::new(),
+            account_info: String::new(),
+            tasks_string: String::new(),
+            reward_icon_url: String::new(),
+            reward_info: String::new(),
+            quest_id: "1",
+        };
+        let payload = build_webhook_payload(&data);
+        assert_eq!(
+            payload["components"][0]["components"][0]["content"],
+            "## **Quest Expired** - [Game](https://discord.com/quests/1)"
+        );
+    }
+}
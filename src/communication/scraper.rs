@@ -1,6 +1,8 @@
-use crate::config::AppConfig;
+use crate::config::Account;
+use crate::metrics::metrics;
 use crate::types::error::AppError;
 use crate::types::quest::DiscordQuest;
+use crate::types::secret::body_excerpt;
 use reqwest::Client;
 use std::time::Duration;
 use tracing::{info, debug, warn};
@@ -8,6 +10,7 @@ use tracing::{info, debug, warn};
 // Discord API configuration
 const DISCORD_API_BASE: &str = "https://discord.com/api/v9";
 const QUESTS_ENDPOINT: &str = "quests/@me";
+const CURRENT_USER_ENDPOINT: &str = "users/@me";
 const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
 const REQUEST_TIMEOUT_SECS: u64 = 30;
 const CONNECT_TIMEOUT_SECS: u64 = 10;
@@ -19,39 +22,55 @@ const CONNECT_TIMEOUT_SECS: u64 = 10;
 /// Returns `AppError` if:
 /// - HTTP client construction fails
 /// - Request to Discord API fails
+/// - Discord API rejects the token (`AppError::Unauthorized`)
 /// - Discord API returns non-success status
 /// - Response parsing fails
-pub async fn fetch_quests(config: &AppConfig, region: &str) -> Result<Vec<DiscordQuest>, AppError> {
-    let client = Client::builder()
-        .user_agent(USER_AGENT)
-        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
-        .connect_timeout(Duration::from_secs(CONNECT_TIMEOUT_SECS))
-        .build()
-        .map_err(|e| AppError::Request(e.to_string()))?;
-        
+pub async fn fetch_quests(account: &Account, region: &str) -> Result<Vec<DiscordQuest>, AppError> {
+    let client = api_client()?;
     let url = format!("{DISCORD_API_BASE}/{QUESTS_ENDPOINT}");
+    let fetch_error = |status: &str| metrics().fetch_errors.with_label_values(&[status]).inc();
 
+    let timer = metrics().api_latency.with_label_values(&["quests"]).start_timer();
     let response = client.get(&url)
-        .header("Authorization", &config.discord_auth_token)
-        .header("x-super-properties", &config.super_properties)
+        .header("Authorization", account.token.expose())
+        .header("x-super-properties", &account.super_properties)
         .header("x-discord-locale", region)
         .send()
         .await
         .map_err(|e| {
-            warn!("Failed to fetch quests from Discord API: {}", e);
-            AppError::Request(e.to_string())
+            fetch_error("network");
+            let error = AppError::Request(format!("Request to {url} failed: {}", e.without_url()));
+            warn!(status = "network", "Failed to fetch quests from Discord API for account {}: {}", account.name, error);
+            error
         })?;
+    timer.observe_duration();
 
     let status = response.status();
     if !status.is_success() {
-        let error_body = response.text().await.unwrap_or_default();
-        warn!("Discord API returned error status {}: {}", status, error_body);
+        fetch_error(status.as_str());
+    }
+    if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+        warn!(status = status.as_str(), "Discord API rejected the auth token of account {} with status {}", account.name, status);
+        return Err(AppError::Unauthorized(format!("Discord API returned {status} for account {}; check its token", account.name)));
+    }
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = response.headers().get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+        warn!(status = status.as_str(), "Discord API rate limited account {} (Retry-After: {:?})", account.name, retry_after);
+        return Err(AppError::RateLimited(format!("Discord API returned {status} for account {}", account.name), retry_after));
+    }
+    if !status.is_success() {
+        let error_body = body_excerpt(&response.text().await.unwrap_or_default(), &account.token);
+        warn!(status = status.as_str(), "Discord API returned error status {}: {}", status, error_body);
         return Err(AppError::Config(format!("Discord API error: {status} - {error_body}")));
     }
     
     let body: serde_json::Value = response.json().await.map_err(|e| {
+        fetch_error("invalid_response");
+        let e = e.without_url();
         warn!("Failed to parse Discord API response as JSON: {}", e);
-        AppError::Request(e.to_string())
+        AppError::Request(format!("Invalid response from {url}: {e}"))
     })?;
     
     debug!("API Response: {:?}", body);
@@ -59,21 +78,102 @@ pub async fn fetch_quests(config: &AppConfig, region: &str) -> Result<Vec<Discor
     if let Some(quests_array) = body.get("quests") {
         let quests: Vec<DiscordQuest> = serde_json::from_value(quests_array.clone())
             .map_err(|e| {
+                fetch_error("invalid_response");
                 warn!("Failed to parse quests array from 'quests' field: {}", e);
                 AppError::Parse(format!("Failed to parse quests array from 'quests' field: {e}"))
             })?;
-        info!("Successfully fetched {} quests from region {}", quests.len(), region);
+        info!("Successfully fetched {} quests from region {} for account {}", quests.len(), region, account.name);
         Ok(quests)
     } else if body.is_array() {
         let quests: Vec<DiscordQuest> = serde_json::from_value(body)
             .map_err(|e| {
+                fetch_error("invalid_response");
                 warn!("Failed to parse quests array: {}", e);
                 AppError::Parse(format!("Failed to parse quests array: {e}"))
             })?;
-        info!("Successfully fetched {} quests from region {}", quests.len(), region);
+        info!("Successfully fetched {} quests from region {} for account {}", quests.len(), region, account.name);
         Ok(quests)
     } else {
         warn!("Unexpected API response structure (no 'quests' field and not an array). Response: {:?}", body);
         Ok(vec![])
     }
 }
+
+/// Check the auth token against a lightweight endpoint, returning the account's username
+///
+/// # Errors
+///
+/// Returns `AppError::Unauthorized` if Discord rejects the token, or another `AppError`
+/// if the request fails or the response is not a user
+pub async fn fetch_current_user(account: &Account) -> Result<String, AppError> {
+    let user = get_json(account, CURRENT_USER_ENDPOINT).await?;
+    user.get("username")
+        .and_then(serde_json::Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| AppError::Parse(format!("Unexpected response from {CURRENT_USER_ENDPOINT}: no username")))
+}
+
+/// Name of a channel or guild the account can see, e.g. `channels/<id>`, or `None` if
+/// it cannot be read
+pub async fn fetch_name(account: &Account, path: &str) -> Option<String> {
+    match get_json(account, path).await {
+        Ok(object) => object.get("name").and_then(serde_json::Value::as_str).map(str::to_string),
+        Err(e) => {
+            debug!("Could not read {}: {}", path, e);
+            None
+        }
+    }
+}
+
+async fn get_json(account: &Account, path: &str) -> Result<serde_json::Value, AppError> {
+    let url = format!("{DISCORD_API_BASE}/{path}");
+    // Only the first path segment, so IDs do not become label values
+    let endpoint = path.split('/').next().unwrap_or_default();
+    let timer = metrics().api_latency.with_label_values(&[endpoint]).start_timer();
+    let response = api_client()?
+        .get(&url)
+        .header("Authorization", account.token.expose())
+        .header("x-super-properties", &account.super_properties)
+        .send()
+        .await
+        .map_err(|e| AppError::Request(format!("Request to {url} failed: {}", e.without_url())))?;
+    timer.observe_duration();
+
+    let status = response.status();
+    if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+        return Err(AppError::Unauthorized(format!("Discord API returned {status} for {path} with account {}; check its token", account.name)));
+    }
+    if !status.is_success() {
+        let body = body_excerpt(&response.text().await.unwrap_or_default(), &account.token);
+        return Err(AppError::Request(format!("Discord API error for {path}: {status} - {body}")));
+    }
+    response.json().await
+        .map_err(|e| AppError::Parse(format!("Invalid response from {url}: {}", e.without_url())))
+}
+
+fn api_client() -> Result<Client, AppError> {
+    Client::builder()
+        .user_agent(USER_AGENT)
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .connect_timeout(Duration::from_secs(CONNECT_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| AppError::Request(e.to_string()))
+}
+
+/// Seconds in a `Retry-After` header; Discord may send fractions
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    value.trim().parse::<f64>().ok().and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_after_accepts_fractional_seconds() {
+        assert_eq!(parse_retry_after("5"), Some(Duration::from_secs(5)));
+        assert_eq!(parse_retry_after(" 1.5 "), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_retry_after("-1"), None);
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+    }
+}
@@ -0,0 +1,53 @@
+use crate::config::TelegramDestination;
+use crate::types::error::AppError;
+use crate::types::secret::body_excerpt;
+use crate::communication::text::QuestText;
+use reqwest::Client;
+use serde_json::json;
+
+const TELEGRAM_API_BASE: &str = "https://api.telegram.org";
+
+/// Send a quest message to every chat of a Telegram destination
+///
+/// # Errors
+///
+/// Returns `AppError` if the Bot API request fails or returns a non-success status
+pub async fn send_message(
+    client: &Client,
+    destination: &TelegramDestination,
+    text: &QuestText,
+) -> Result<(), AppError> {
+    let url = format!("{TELEGRAM_API_BASE}/bot{}/sendMessage", destination.bot_token.expose());
+    let message = format_message(text);
+
+    for chat_id in &destination.chat_ids {
+        let res = client.post(&url)
+            .json(&json!({
+                "chat_id": chat_id,
+                "text": message,
+                "parse_mode": "HTML",
+            }))
+            .send()
+            .await
+            // The request URL embeds the bot token, so only the error kind is reported
+            .map_err(|e| AppError::Request(format!("Telegram request failed: {}", e.without_url())))?;
+
+        if !res.status().is_success() {
+            let status = res.status();
+            let body = body_excerpt(&res.text().await.unwrap_or_default(), &destination.bot_token);
+            return Err(AppError::Request(format!("Telegram sendMessage failed for chat {chat_id}: {status} - {body}")));
+        }
+    }
+
+    Ok(())
+}
+
+/// HTML message sent to each chat
+#[must_use]
+pub fn format_message(text: &QuestText) -> String {
+    format!("<b>{}</b>\n\n{}", escape_html(&text.title), escape_html(&text.body))
+}
+
+fn escape_html(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
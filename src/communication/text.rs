@@ -0,0 +1,135 @@
+use crate::types::digest::{DigestQuest, QuestDigest};
+use crate::types::event::{QuestEvent, QuestEventKind};
+use crate::communication::task::format_task_description;
+use chrono::DateTime;
+use std::fmt::Write as FmtWrite;
+
+/// Most notifications listed in a quiet hours digest, and quests in each section of a
+/// scheduled digest
+const MAX_DIGEST_ITEMS: usize = 10;
+
+/// Plain-text rendering of a quest event for chat and email destinations
+pub struct QuestText {
+    pub title: String,
+    pub body: String,
+}
+
+/// Render a quest event as a title and a multi-line plain-text body
+#[must_use]
+pub fn format_quest_text(event: &QuestEvent) -> QuestText {
+    let quest = &event.quest;
+    let computed = &event.computed;
+
+    let title = format!("{} - {}", heading(event.event), quest.config.messages.game_title);
+
+    let mut body = format!(
+        "Game: {} ({})",
+        quest.config.messages.game_title, quest.config.messages.game_publisher
+    );
+    match (&computed.reward_name, &computed.reward_type) {
+        (Some(name), Some(reward_type)) => { let _ = write!(body, "\nReward: {name} ({reward_type})"); }
+        (Some(name), None) => { let _ = write!(body, "\nReward: {name}"); }
+        _ => body.push_str("\nReward: No reward details available"),
+    }
+    if let Some(orbs) = computed.orb_quantity {
+        let _ = write!(body, "\nOrbs: {orbs}");
+    }
+    let _ = write!(
+        body,
+        "\nDuration: {} - {}",
+        format_date(computed.starts_at_unix),
+        format_date(computed.expires_at_unix)
+    );
+
+    if !event.accounts.is_empty() {
+        let _ = write!(body, "\nAccounts: {}", event.accounts.join(", "));
+    }
+    if let Some(progress) = event.progress_summary() {
+        let _ = write!(body, "\nProgress: {progress}");
+    }
+
+    body.push_str("\nTasks:");
+    for task in quest.config.task_config.tasks.values() {
+        body.push('\n');
+        body.push_str(&format_task_description(&task.event_name, task.target));
+    }
+    let _ = write!(body, "\n\n{}", computed.quest_url);
+
+    QuestText { title, body }
+}
+
+/// Render the notifications held during quiet hours as one message, listing at most
+/// `MAX_DIGEST_ITEMS` of them so it fits in a chat message
+#[must_use]
+pub fn format_quiet_digest(events: &[QuestEvent]) -> QuestText {
+    let title = format!("{} quest notifications from quiet hours", events.len());
+    let mut body = String::new();
+    for event in events.iter().take(MAX_DIGEST_ITEMS) {
+        let reward = event.computed.reward_name.as_deref().unwrap_or("No reward details available");
+        let _ = write!(
+            body,
+            "{}: {} - {reward}\n{}\n\n",
+            heading(event.event), event.quest.config.messages.game_title, event.computed.quest_url
+        );
+    }
+    if events.len() > MAX_DIGEST_ITEMS {
+        let _ = write!(body, "...and {} more", events.len() - MAX_DIGEST_ITEMS);
+    }
+    QuestText { title, body: body.trim_end().to_string() }
+}
+
+/// Render a scheduled quest digest for chat and email destinations
+#[must_use]
+pub fn format_digest(digest: &QuestDigest) -> QuestText {
+    let title = format!("Quest Digest: {} active quests", digest.active);
+    let mut body = format!("Orbs available: {}", digest.total_orbs);
+
+    let _ = write!(body, "\n\nEnding in the next {} hours:", digest.ending_hours);
+    push_digest_quests(&mut body, &digest.ending_soon, |quest| format!(" (ends {})", format_date(quest.expires_at_unix)));
+
+    let _ = write!(body, "\n\nNew since {}:", digest.period_start.format("%Y-%m-%d %H:%M UTC"));
+    push_digest_quests(&mut body, &digest.new_quests, |quest| {
+        quest.reward_name.as_ref().map(|name| format!(" - {name}")).unwrap_or_default()
+    });
+
+    body.push_str("\n\nBy reward type:");
+    if digest.reward_types.is_empty() {
+        body.push_str("\nNone");
+    }
+    for (reward_type, quests) in &digest.reward_types {
+        let orbs: i64 = quests.iter().map(|quest| i64::from(quest.orb_quantity.unwrap_or(0))).sum();
+        let orbs = if orbs > 0 { format!(", {orbs} orbs") } else { String::new() };
+        let _ = write!(body, "\n{reward_type} ({}{orbs})", quests.len());
+    }
+
+    QuestText { title, body }
+}
+
+/// Append one `- Game` line per quest, followed by `detail`
+fn push_digest_quests(body: &mut String, quests: &[DigestQuest], detail: impl Fn(&DigestQuest) -> String) {
+    if quests.is_empty() {
+        body.push_str("\nNone");
+    }
+    for quest in quests.iter().take(MAX_DIGEST_ITEMS) {
+        let _ = write!(body, "\n- {}{}\n  {}", quest.game_title, detail(quest), quest.quest_url);
+    }
+    if quests.len() > MAX_DIGEST_ITEMS {
+        let _ = write!(body, "\n...and {} more", quests.len() - MAX_DIGEST_ITEMS);
+    }
+}
+
+/// Title of a notification about an event of this kind
+#[must_use]
+pub const fn heading(kind: QuestEventKind) -> &'static str {
+    match kind {
+        QuestEventKind::Created => "New Quest",
+        QuestEventKind::Updated => "Quest Updated",
+        QuestEventKind::Expired => "Quest Expired",
+    }
+}
+
+fn format_date(timestamp: Option<i64>) -> String {
+    timestamp
+        .and_then(|ts| DateTime::from_timestamp(ts, 0))
+        .map_or_else(|| "Unknown".to_string(), |dt| dt.format("%Y-%m-%d %H:%M UTC").to_string())
+}
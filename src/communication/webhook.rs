@@ -1,6 +1,9 @@
 use crate::config::AppConfig;
+use crate::metrics::metrics;
 use crate::types::error::AppError;
-use crate::types::quest::DiscordQuest;
+use crate::types::digest::QuestDigest;
+use crate::types::event::QuestEvent;
+use crate::types::secret::{body_excerpt, Secret};
 use crate::types::constants::{
     COLOR_DEFAULT, COLOR_VIRTUAL_CURRENCY, COLOR_COLLECTIBLE,
     REWARD_TYPE_VIRTUAL_CURRENCY, REWARD_TYPE_COLLECTIBLE,
@@ -10,12 +13,14 @@ use crate::communication::{
     collectibles::resolve_reward_icon_url,
     reward::format_reward_info,
     task::format_task_description,
-    payload::{build_webhook_payload, WebhookPayloadData},
+    payload::{build_digest_embed, build_digest_payload, build_webhook_payload, WebhookPayloadData},
+    text::{self, QuestText},
 };
 use chrono::DateTime;
 use reqwest::Client;
+use std::fmt::Write as FmtWrite;
 
-/// Send a Discord webhook notification for a new quest
+/// Send a Discord webhook notification for a quest event
 ///
 /// # Errors
 ///
@@ -26,8 +31,150 @@ use reqwest::Client;
 pub async fn send_webhook(
     client: &Client,
     config: &AppConfig,
-    quest: &DiscordQuest,
+    webhook_url: &Secret,
+    event: &QuestEvent,
 ) -> Result<(), AppError> {
+    let payload = build_quest_payload(client, config, event).await?;
+
+    // Send webhook
+    let separator = if webhook_url.expose().contains('?') { '&' } else { '?' };
+    let url = format!("{}{separator}with_components=true", webhook_url.expose());
+
+    // The URL embeds the webhook token, so it is left out of errors
+    let timer = metrics().webhook_latency.start_timer();
+    let res = client.post(&url)
+        .json(&payload)
+        .send()
+        .await
+        .map_err(|e| AppError::Request(format!("Webhook request failed: {}", e.without_url())))?;
+    timer.observe_duration();
+
+    if !res.status().is_success() {
+        let status = res.status();
+        let body = body_excerpt(&res.text().await.unwrap_or_default(), &webhook_token(webhook_url));
+        return Err(AppError::Config(format!("Webhook failed: {status} - {body}")));
+    }
+
+    Ok(())
+}
+
+/// Send a plain-text message, used for operational alerts
+///
+/// # Errors
+///
+/// Returns `AppError` if the request fails or the webhook returns a non-success status
+pub async fn send_text(client: &Client, webhook_url: &Secret, text: &QuestText) -> Result<(), AppError> {
+    let timer = metrics().webhook_latency.start_timer();
+    let res = client.post(webhook_url.expose())
+        .json(&serde_json::json!({ "content": format!("**{}**\n{}", text.title, text.body) }))
+        .send()
+        .await
+        .map_err(|e| AppError::Request(format!("Webhook request failed: {}", e.without_url())))?;
+    timer.observe_duration();
+
+    if !res.status().is_success() {
+        let status = res.status();
+        let body = body_excerpt(&res.text().await.unwrap_or_default(), &webhook_token(webhook_url));
+        return Err(AppError::Request(format!("Webhook failed: {status} - {body}")));
+    }
+    Ok(())
+}
+
+/// Send a scheduled quest digest as a Components V2 message, falling back to a plain
+/// embed if Discord rejects the message (e.g. because it is too long)
+///
+/// # Errors
+///
+/// Returns `AppError` if the request fails or the webhook rejects both messages
+pub async fn send_digest(client: &Client, webhook_url: &Secret, digest: &QuestDigest) -> Result<(), AppError> {
+    let separator = if webhook_url.expose().contains('?') { '&' } else { '?' };
+    let url = format!("{}{separator}with_components=true", webhook_url.expose());
+    let payload = build_digest_payload(digest, COLOR_DEFAULT);
+
+    let timer = metrics().webhook_latency.start_timer();
+    let res = client.post(&url)
+        .json(&payload)
+        .send()
+        .await
+        .map_err(|e| AppError::Request(format!("Webhook request failed: {}", e.without_url())))?;
+    timer.observe_duration();
+
+    let status = res.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let body = body_excerpt(&res.text().await.unwrap_or_default(), &webhook_token(webhook_url));
+    if status != reqwest::StatusCode::BAD_REQUEST {
+        return Err(AppError::Request(format!("Webhook failed: {status} - {body}")));
+    }
+
+    tracing::warn!("Discord rejected the digest message ({body}), sending it as an embed instead");
+    let timer = metrics().webhook_latency.start_timer();
+    let res = client.post(webhook_url.expose())
+        .json(&build_digest_embed(digest, COLOR_DEFAULT))
+        .send()
+        .await
+        .map_err(|e| AppError::Request(format!("Webhook request failed: {}", e.without_url())))?;
+    timer.observe_duration();
+
+    if !res.status().is_success() {
+        let status = res.status();
+        let body = body_excerpt(&res.text().await.unwrap_or_default(), &webhook_token(webhook_url));
+        return Err(AppError::Request(format!("Webhook failed: {status} - {body}")));
+    }
+    Ok(())
+}
+
+/// What Discord reports about an existing webhook
+#[derive(serde::Deserialize)]
+pub struct WebhookInfo {
+    pub name: Option<String>,
+    pub channel_id: Option<String>,
+    pub guild_id: Option<String>,
+}
+
+/// Look up a webhook by its URL, confirming it still exists
+///
+/// # Errors
+///
+/// Returns `AppError::NotFound` if Discord rejects the webhook id or token (it was deleted
+/// or the token is wrong), or another `AppError` if the request fails
+pub async fn fetch_webhook_info(client: &Client, webhook_url: &Secret) -> Result<WebhookInfo, AppError> {
+    let res = client.get(webhook_url.expose())
+        .send()
+        .await
+        .map_err(|e| AppError::Request(format!("Webhook request failed: {}", e.without_url())))?;
+
+    let status = res.status();
+    if status == reqwest::StatusCode::NOT_FOUND || status == reqwest::StatusCode::UNAUTHORIZED {
+        return Err(AppError::NotFound(format!("Discord reports the webhook as unknown ({status}); it was deleted or its token is wrong")));
+    }
+    if !status.is_success() {
+        let body = body_excerpt(&res.text().await.unwrap_or_default(), &webhook_token(webhook_url));
+        return Err(AppError::Request(format!("Webhook lookup failed: {status} - {body}")));
+    }
+    res.json().await
+        .map_err(|e| AppError::Parse(format!("Invalid webhook lookup response: {}", e.without_url())))
+}
+
+/// The token part of a webhook URL, for scrubbing it from response bodies
+fn webhook_token(webhook_url: &Secret) -> Secret {
+    Secret::new(webhook_url.expose().split('?').next().unwrap_or_default().rsplit('/').next().unwrap_or_default())
+}
+
+/// Build the Components V2 webhook body for a quest event, resolving the reward icon
+///
+/// The event's accounts and their progress are listed under the quest info.
+///
+/// # Errors
+///
+/// Returns `AppError::Parse` if the quest start or expiry date is invalid
+pub async fn build_quest_payload(
+    client: &Client,
+    config: &AppConfig,
+    event: &QuestEvent,
+) -> Result<serde_json::Value, AppError> {
+    let quest = &event.quest;
     let reward = quest.config.rewards_config.rewards.first();
     
     // Determine accent color based on reward type
@@ -64,11 +211,19 @@ pub async fn send_webhook(
         .collect::<Vec<_>>()
         .join("\n");
 
+    let mut account_info = String::new();
+    if !event.accounts.is_empty() {
+        let _ = write!(account_info, "\n**Visible to**: {}", event.accounts.join(", "));
+    }
+    if let Some(progress) = event.progress_summary() {
+        let _ = write!(account_info, "\n**Progress**: {progress}");
+    }
+
     // Format reward info
     let reward_info = format_reward_info(reward);
 
-    // Build payload
-    let payload = build_webhook_payload(&WebhookPayloadData {
+    Ok(build_webhook_payload(&WebhookPayloadData {
+        heading: text::heading(event.event),
         accent_color,
         game_title: &quest.config.messages.game_title,
         game_publisher: &quest.config.messages.game_publisher,
@@ -80,28 +235,10 @@ pub async fn send_webhook(
         app_link: &quest.config.application.link,
         app_id: &quest.config.application.id,
         features_string,
+        account_info,
         tasks_string,
         reward_icon_url,
         reward_info,
         quest_id: &quest.id,
-    });
-
-    // Send webhook
-    let separator = if config.discord_webhook_url.contains('?') { '&' } else { '?' };
-    let webhook_url = format!("{}{separator}with_components=true", config.discord_webhook_url);
-
-    let res = client.post(&webhook_url)
-        .json(&payload)
-        .send()
-        .await
-        .map_err(|e| AppError::Request(e.to_string()))?;
-
-    if !res.status().is_success() {
-        let status = res.status();
-        let body = res.text().await.unwrap_or_default();
-        tracing::error!("Webhook failed. Status: {}, Body: {}", status, body);
-        return Err(AppError::Config(format!("Webhook failed: {status} - {body}")));
-    }
-
-    Ok(())
+    }))
 }
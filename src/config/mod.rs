@@ -1,100 +1,719 @@
+pub mod destination;
+pub mod polling;
+pub mod reload;
+mod source;
+
+pub use destination::{
+    Destination, EmailDestination, JsonWebhookConfig, NtfyDestination, Route, TelegramDestination,
+};
+
+pub use polling::{CronSchedule, PollingConfig};
+pub use source::ConfigLocation;
+
 use crate::types::error::AppError;
-use std::env;
+use crate::types::secret::Secret;
+use source::ConfigSource;
+use destination::{DEFAULT_JSON_WEBHOOK_MAX_RETRIES, MAX_JSON_WEBHOOK_MAX_RETRIES};
+use chrono::{DateTime, TimeDelta, Utc};
 use dotenvy::dotenv;
+use std::net::SocketAddr;
+use std::path::PathBuf;
 
 // Configuration validation constants
-const MIN_POLLING_INTERVAL_SEC: u64 = 30;
-const MAX_POLLING_INTERVAL_SEC: u64 = 86400; // 24 hours
+pub const MIN_POLLING_INTERVAL_SEC: u64 = 30;
+pub const MAX_POLLING_INTERVAL_SEC: u64 = 86400; // 24 hours
 const DEFAULT_POLLING_INTERVAL_SEC: u64 = 300; // 5 minutes
 const DEFAULT_REGION: &str = "en-US";
-const DISCORD_WEBHOOK_PREFIX_1: &str = "https://discord.com/api/webhooks/";
-const DISCORD_WEBHOOK_PREFIX_2: &str = "https://discordapp.com/api/webhooks/";
-const DISCORD_WEBHOOK_PREFIX_3: &str = "https://ptb.discord.com/api/webhooks/";
-const DISCORD_WEBHOOK_PREFIX_4: &str = "https://canary.discord.com/api/webhooks/";
+const DEFAULT_ACCOUNT_NAME: &str = "default";
+const DEFAULT_MQTT_PORT: u16 = 1883;
+const DEFAULT_MQTT_TLS_PORT: u16 = 8883;
+const DEFAULT_MQTT_CLIENT_ID: &str = "discord-quest-notification";
+const DEFAULT_MQTT_TOPIC_PREFIX: &str = "discord/quests";
+const DEFAULT_MQTT_QOS: u8 = 1;
+const DEFAULT_CALENDAR_REMINDER_HOURS: &str = "24";
+const MAX_CALENDAR_REMINDER_HOURS: u32 = 720; // 30 days
+const DEFAULT_READY_POLL_INTERVALS: u32 = 3;
+const DEFAULT_READY_MAX_PENDING: usize = 100;
+const DEFAULT_LOG_MAX_FILES: usize = 7;
+const DEFAULT_ALERT_FETCH_FAILURES: u32 = 3;
+const DEFAULT_ALERT_DELIVERY_FAILURES: u32 = 5;
+const DEFAULT_SHUTDOWN_TIMEOUT_SEC: u64 = 10;
+const MAX_SHUTDOWN_TIMEOUT_SEC: u64 = 300;
+const DEFAULT_DIGEST_SCHEDULE: &str = "0 9 * * *"; // daily at 09:00
+const DEFAULT_DIGEST_ENDING_HOURS: u32 = 48;
+const MAX_DIGEST_ENDING_HOURS: u32 = 720; // 30 days
+
+/// iCalendar export settings
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct CalendarConfig {
+    pub path: Option<String>,
+    pub all_day: bool,
+    pub reminder_hours: Vec<u32>,
+}
+
+/// MQTT broker connection and topic settings
+#[derive(Clone, PartialEq, Eq)]
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<Secret>,
+    pub topic_prefix: String,
+    pub qos: u8,
+    pub tls: bool,
+    pub ca_file: Option<String>,
+}
+
+/// When `/readyz` reports the monitor as ready
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReadinessConfig {
+    /// The last successful poll must be at most this many polling intervals old
+    pub poll_intervals: u32,
+    /// Most failed deliveries that may wait in the outbox
+    pub max_pending: usize,
+}
+
+/// When the monitor alerts the ops destinations about its own failures; 0 disables an alert
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AlertConfig {
+    /// Consecutive polls in which an account's fetch failed
+    pub fetch_failures: u32,
+    /// Consecutive failed deliveries to a destination
+    pub delivery_failures: u32,
+}
+
+/// Scheduled summaries of the active quests
+#[derive(Clone, PartialEq, Eq)]
+pub struct DigestConfig {
+    pub destinations: Vec<Destination>,
+    pub schedule: CronSchedule,
+    /// Quests expiring within this many hours are listed as ending soon
+    pub ending_hours: u32,
+}
+
+impl DigestConfig {
+    /// Start of the period covered by the digest sent at `now`: the previous digest, or
+    /// the previous scheduled time when no digest was sent yet
+    #[must_use]
+    pub fn period_start(&self, last_digest_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> DateTime<Utc> {
+        last_digest_at
+            .or_else(|| self.schedule.previous_before(now))
+            .unwrap_or_else(|| now - TimeDelta::days(1))
+    }
+}
+
+/// A Discord account whose quests are polled
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Account {
+    /// Unique name used in logs and in notifications listing who can see a quest
+    pub name: String,
+    pub token: Secret,
+    pub super_properties: String,
+    /// Regions to poll, never empty
+    pub regions: Vec<String>,
+}
+
+/// How startup checks of the token and Discord webhooks treat a definite failure
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PreflightMode {
+    /// Refuse to start
+    #[default]
+    Strict,
+    /// Log a warning and start anyway (degraded mode)
+    Warn,
+    /// Skip the checks
+    Off,
+}
+
+/// Format of log lines
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, with span fields such as `region` and `destination`
+    Json,
+}
+
+/// When the log file is rotated
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogRotation {
+    Never,
+    Hourly,
+    #[default]
+    Daily,
+    /// Once the file would grow beyond this many bytes
+    Size(u64),
+}
+
+/// Log output of `run` and `once`; other commands always log to stderr
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LoggingConfig {
+    pub format: LogFormat,
+    /// Log to this file instead of stdout
+    pub file: Option<PathBuf>,
+    pub rotation: LogRotation,
+    /// Rotated files to keep; 0 keeps all
+    pub max_files: usize,
+}
+
+/// Where `--dry-run` writes rendered notifications instead of sending them
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DryRunOutput {
+    Stdout,
+    /// One JSON file per notification in this directory
+    Dir(PathBuf),
+}
 
 #[derive(Clone)]
 pub struct AppConfig {
-    pub discord_auth_token: String,
-    pub discord_webhook_url: String,
+    /// Discord accounts to poll; never empty, the first one is the primary account
+    pub accounts: Vec<Account>,
     pub polling_interval_sec: u64,
-    pub discord_regions: Vec<String>,
+    pub polling: PollingConfig,
     pub previous_quests: bool,
-    pub super_properties: String,
+    pub destinations: Vec<Route>,
+    /// Where operational alerts go, e.g. when Discord rejects the token
+    pub ops_destinations: Vec<Destination>,
+    pub mqtt: Option<MqttConfig>,
+    pub feed_atom_path: Option<String>,
+    pub feed_rss_path: Option<String>,
+    pub calendar: CalendarConfig,
+    pub state_file: Option<String>,
+    pub preflight: PreflightMode,
+    /// Address of the HTTP listener serving metrics and health endpoints; `None` disables it
+    pub http_addr: Option<SocketAddr>,
+    pub readiness: ReadinessConfig,
+    pub alerts: AlertConfig,
+    /// Scheduled summary of the active quests; `None` without `DIGEST_URLS`
+    pub digest: Option<DigestConfig>,
+    /// How long a shutdown waits for the notifications being sent
+    pub shutdown_timeout_sec: u64,
+    pub logging: LoggingConfig,
+    /// Set from the command line; never read from the environment or config file
+    pub dry_run: Option<DryRunOutput>,
+    pub location: ConfigLocation,
 }
 
 impl AppConfig {
-    /// Load application configuration from environment variables
+    /// The first configured account, used by commands that query a single account
+    #[must_use]
+    pub fn primary_account(&self) -> &Account {
+        &self.accounts[0]
+    }
+
+    /// Look up an account by name, or the primary account for `None`
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::NotFound` if no account has that name
+    pub fn account(&self, name: Option<&str>) -> Result<&Account, AppError> {
+        let Some(name) = name else { return Ok(self.primary_account()) };
+        self.accounts.iter().find(|a| a.name == name).ok_or_else(|| {
+            let names: Vec<&str> = self.accounts.iter().map(|a| a.name.as_str()).collect();
+            AppError::NotFound(format!("Account '{name}' is not configured (available: {names:?})"))
+        })
+    }
+
+    /// Load application configuration from the config file (if any) and environment variables
+    ///
+    /// Environment variables override the selected profile, which overrides `[default]`.
     ///
     /// # Errors
     ///
     /// Returns `AppError` if:
-    /// - Required environment variables are missing or empty
-    /// - Webhook URL format is invalid
+    /// - The config file cannot be read or parsed, or the profile does not exist
+    /// - Required settings are missing or empty
+    /// - A webhook or notification URL is invalid
+    /// - No notification destination is configured
     /// - Polling interval is out of valid range
-    pub fn load() -> Result<Self, AppError> {
-        dotenv().ok();
+    pub fn load(location: &ConfigLocation) -> Result<Self, AppError> {
+        let config = Self::load_without_destinations(location)?;
 
-        let token = env::var("DISCORD_AUTH_TOKEN")
-            .map_err(|_| AppError::Config("DISCORD_AUTH_TOKEN not set".to_string()))?;
-        
-        if token.trim().is_empty() {
-            return Err(AppError::Config("DISCORD_AUTH_TOKEN is empty or contains only whitespace".to_string()));
+        if config.destinations.is_empty() && config.mqtt.is_none() {
+            return Err(AppError::Config(
+                "No notification destination configured: set DISCORD_WEBHOOK_URL, NOTIFY_URLS, JSON_WEBHOOK_URL or MQTT_HOST".to_string()
+            ));
         }
 
-        let webhook = env::var("DISCORD_WEBHOOK_URL")
-            .map_err(|_| AppError::Config("DISCORD_WEBHOOK_URL not set".to_string()))?;
-        
-        if webhook.trim().is_empty() {
-            return Err(AppError::Config("DISCORD_WEBHOOK_URL is empty or contains only whitespace".to_string()));
-        }
-        
-        if !webhook.starts_with(DISCORD_WEBHOOK_PREFIX_1) 
-            && !webhook.starts_with(DISCORD_WEBHOOK_PREFIX_2)
-            && !webhook.starts_with(DISCORD_WEBHOOK_PREFIX_3)
-            && !webhook.starts_with(DISCORD_WEBHOOK_PREFIX_4) {
-            return Err(AppError::Config("DISCORD_WEBHOOK_URL must be a valid Discord webhook URL".to_string()));
-        }
+        Ok(config)
+    }
 
-        let interval_str = env::var("POLLING_INTERVAL_SEC")
-            .unwrap_or_else(|_| DEFAULT_POLLING_INTERVAL_SEC.to_string());
+    /// Load the configuration like [`AppConfig::load`], but accept a config without any
+    /// notification destination. Used by read-only commands such as `list` and `preview`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError` for the same reasons as [`AppConfig::load`], except a missing destination
+    pub fn load_without_destinations(location: &ConfigLocation) -> Result<Self, AppError> {
+        dotenv().ok();
+        let location = location.clone().with_env_defaults();
+        let source = ConfigSource::load(&location)?;
+
+        let interval_str = source.get("POLLING_INTERVAL_SEC")
+            .unwrap_or_else(|| DEFAULT_POLLING_INTERVAL_SEC.to_string());
         let polling_interval_sec = interval_str.parse::<u64>()
-            .map_err(|e| AppError::Config(format!("Invalid POLLING_INTERVAL_SEC: {e}")))?;
+            .map_err(|e| source.error("POLLING_INTERVAL_SEC", &format!("Invalid POLLING_INTERVAL_SEC: {e}")))?;
         
         if polling_interval_sec < MIN_POLLING_INTERVAL_SEC {
-            return Err(AppError::Config(format!(
+            return Err(source.error("POLLING_INTERVAL_SEC", &format!(
                 "POLLING_INTERVAL_SEC must be at least {MIN_POLLING_INTERVAL_SEC} seconds to avoid rate limiting"
             )));
         }
         
         if polling_interval_sec > MAX_POLLING_INTERVAL_SEC {
-            return Err(AppError::Config(format!(
+            return Err(source.error("POLLING_INTERVAL_SEC", &format!(
                 "POLLING_INTERVAL_SEC must be less than {MAX_POLLING_INTERVAL_SEC} seconds (24 hours)"
             )));
         }
 
-        let regions_str = env::var("DISCORD_REGIONS")
-            .unwrap_or_else(|_| DEFAULT_REGION.to_string());
-        let discord_regions: Vec<String> = regions_str.split(',')
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect();
+        let polling = polling::load_polling(&source)?;
 
-        let previous_quests = env::var("PREVIOUS_QUEST")
-            .unwrap_or_else(|_| "false".to_string())
+        let previous_quests = source.get("PREVIOUS_QUEST")
+            .unwrap_or_else(|| "false".to_string())
             .trim()
             .eq_ignore_ascii_case("true");
 
-        let super_properties = env::var("SUPER_PROPERTIES")
-            .unwrap_or_else(|_| "ewogICJvcyI6ICJXaW5kb3dzIiwKICAiYnJvd3NlciI6ICJDaHJvbWUiLAogICJkZXZpY2UiOiAiIiwKICAic3lzdGVtX2xvY2FsZSI6ICJlbi1VUyIsCiAgImJyb3dzZXJfdXNlcl9hZ2VudCI6ICJNb3ppbGxhLzUuMCAoV2luZG93cyBOVCAxMC4wOyBXaW42NDsgeDY0KSBBcHBsZVdlYktpdC81MzcuMzYgKEtIVE1MLCBsaWtlIEdlY2tvKSBDaHJvbWUvMTIwLjAuMC4wIFNhZmFyaS81MzcuMzYiLAogICJicm93c2VyX3ZlcnNpb24iOiAiMTIwLjAuMC4wIiwKICAib3NfdmVyc2lvbiI6ICIxMCIsCiAgInJlZmVycmVyIjogIiIsCiAgInJlZmVycmluZ19kb21haW4iOiAiIiwKICAicmVmZXJyZXJfY3VycmVudCI6ICIiLAogICJyZWZlcnJpbmdfZG9tYWluX2N1cnJlbnQiOiAiIiwKICAicmVsZWFzZV9jaGFubmVsIjogInN0YWJsZSIsCiAgImNsaWVudF9idWlsZF9udW1iZXIiOiA5OTk5OTksCiAgImNsaWVudF9ldmVudF9zb3VyY2UiOiBudWxsCn0d".to_string());
+        let accounts = load_accounts(&source)?;
+        let destinations = load_destinations(&source)?;
+        let ops_destinations = match source.get("OPS_NOTIFY_URLS") {
+            Some(urls) => destination::parse_destination_list(&urls, &source.describe("OPS_NOTIFY_URLS"))?,
+            None => Vec::new(),
+        };
+        let mqtt = load_mqtt(&source)?;
+
+        let feed_atom_path = source.get("FEED_ATOM_PATH").filter(|p| !p.trim().is_empty());
+        let feed_rss_path = source.get("FEED_RSS_PATH").filter(|p| !p.trim().is_empty());
+        let calendar = load_calendar(&source)?;
+        let state_file = source.get("STATE_FILE").filter(|p| !p.trim().is_empty());
+
+        let preflight = match source.get("PREFLIGHT").map(|p| p.trim().to_ascii_lowercase()).as_deref() {
+            None | Some("" | "strict") => PreflightMode::Strict,
+            Some("warn") => PreflightMode::Warn,
+            Some("off") => PreflightMode::Off,
+            Some(other) => return Err(source.error("PREFLIGHT", &format!(
+                "Invalid PREFLIGHT '{other}' (expected strict, warn or off)"
+            ))),
+        };
+
+        let http_addr = source.get("HTTP_ADDR")
+            .filter(|a| !a.trim().is_empty())
+            .map(|addr| addr.trim().parse::<SocketAddr>()
+                .map_err(|e| source.error("HTTP_ADDR", &format!("Invalid HTTP_ADDR '{addr}' (expected e.g. 0.0.0.0:9090): {e}"))))
+            .transpose()?;
+
+        let readiness = load_readiness(&source)?;
+        let alerts = AlertConfig {
+            fetch_failures: load_alert_threshold(&source, "ALERT_FETCH_FAILURES", DEFAULT_ALERT_FETCH_FAILURES)?,
+            delivery_failures: load_alert_threshold(&source, "ALERT_DELIVERY_FAILURES", DEFAULT_ALERT_DELIVERY_FAILURES)?,
+        };
+        let digest = load_digest(&source)?;
+        let shutdown_timeout_sec = match source.get("SHUTDOWN_TIMEOUT_SEC").filter(|v| !v.trim().is_empty()) {
+            Some(value) => value.trim().parse::<u64>().ok()
+                .filter(|secs| (1..=MAX_SHUTDOWN_TIMEOUT_SEC).contains(secs))
+                .ok_or_else(|| source.error("SHUTDOWN_TIMEOUT_SEC", &format!(
+                    "Invalid SHUTDOWN_TIMEOUT_SEC '{value}' (expected 1 to {MAX_SHUTDOWN_TIMEOUT_SEC} seconds)"
+                )))?,
+            None => DEFAULT_SHUTDOWN_TIMEOUT_SEC,
+        };
+        let logging = load_logging(&source)?;
 
         Ok(Self {
-            discord_auth_token: token,
-            discord_webhook_url: webhook,
+            accounts,
             polling_interval_sec,
-            discord_regions,
+            polling,
             previous_quests,
-            super_properties,
+            destinations,
+            ops_destinations,
+            mqtt,
+            feed_atom_path,
+            feed_rss_path,
+            calendar,
+            state_file,
+            preflight,
+            http_addr,
+            readiness,
+            alerts,
+            digest,
+            shutdown_timeout_sec,
+            logging,
+            dry_run: None,
+            location,
         })
     }
 }
 
+/// Build the accounts from `[[account]]` tables, or a single `default` account from
+/// `DISCORD_AUTH_TOKEN`, `SUPER_PROPERTIES` and `DISCORD_REGIONS`. The settings also
+/// provide defaults for fields a table leaves out.
+fn load_accounts(source: &ConfigSource) -> Result<Vec<Account>, AppError> {
+    let token = source.get("DISCORD_AUTH_TOKEN");
+    if token.as_ref().is_some_and(|t| t.trim().is_empty()) {
+        return Err(source.error("DISCORD_AUTH_TOKEN", "DISCORD_AUTH_TOKEN is empty or contains only whitespace"));
+    }
+
+    let super_properties = source.get("SUPER_PROPERTIES")
+        .unwrap_or_else(|| "ewogICJvcyI6ICJXaW5kb3dzIiwKICAiYnJvd3NlciI6ICJDaHJvbWUiLAogICJkZXZpY2UiOiAiIiwKICAic3lzdGVtX2xvY2FsZSI6ICJlbi1VUyIsCiAgImJyb3dzZXJfdXNlcl9hZ2VudCI6ICJNb3ppbGxhLzUuMCAoV2luZG93cyBOVCAxMC4wOyBXaW42NDsgeDY0KSBBcHBsZVdlYktpdC81MzcuMzYgKEtIVE1MLCBsaWtlIEdlY2tvKSBDaHJvbWUvMTIwLjAuMC4wIFNhZmFyaS81MzcuMzYiLAogICJicm93c2VyX3ZlcnNpb24iOiAiMTIwLjAuMC4wIiwKICAib3NfdmVyc2lvbiI6ICIxMCIsCiAgInJlZmVycmVyIjogIiIsCiAgInJlZmVycmluZ19kb21haW4iOiAiIiwKICAicmVmZXJyZXJfY3VycmVudCI6ICIiLAogICJyZWZlcnJpbmdfZG9tYWluX2N1cnJlbnQiOiAiIiwKICAicmVsZWFzZV9jaGFubmVsIjogInN0YWJsZSIsCiAgImNsaWVudF9idWlsZF9udW1iZXIiOiA5OTk5OTksCiAgImNsaWVudF9ldmVudF9zb3VyY2UiOiBudWxsCn0d".to_string());
+    let regions = parse_regions(&source.get("DISCORD_REGIONS").unwrap_or_default());
+
+    let tables = source.account_tables();
+    if tables.is_empty() {
+        let token = token.ok_or_else(|| AppError::Config("DISCORD_AUTH_TOKEN not set".to_string()))?;
+        return Ok(vec![Account {
+            name: DEFAULT_ACCOUNT_NAME.to_string(),
+            token: Secret::new(token),
+            super_properties,
+            regions,
+        }]);
+    }
+
+    let mut accounts: Vec<Account> = Vec::with_capacity(tables.len());
+    for table in tables {
+        let name = table.name.trim().to_string();
+        if name.is_empty() {
+            return Err(AppError::Config(format!("Empty account name in {}", table.location)));
+        }
+        if accounts.iter().any(|a| a.name == name) {
+            return Err(AppError::Config(format!("Duplicate account name in {}", table.location)));
+        }
+        let token = table.token.clone().filter(|t| !t.trim().is_empty())
+            .ok_or_else(|| AppError::Config(format!("Missing token in {}", table.location)))?;
+        accounts.push(Account {
+            name,
+            token: Secret::new(token),
+            super_properties: table.super_properties.clone().unwrap_or_else(|| super_properties.clone()),
+            regions: table.regions.as_ref().map_or_else(|| regions.clone(), |r| parse_regions(&r.join(","))),
+        });
+    }
+    Ok(accounts)
+}
+
+/// Split a comma-separated region list, falling back to the default region
+fn parse_regions(value: &str) -> Vec<String> {
+    let regions: Vec<String> = value.split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+    if regions.is_empty() {
+        vec![DEFAULT_REGION.to_string()]
+    } else {
+        regions
+    }
+}
+
+/// Collect destinations from `DISCORD_WEBHOOK_URL`, `JSON_WEBHOOK_*`, `NOTIFY_URLS`
+/// and `[[destination]]` tables in the config file
+fn load_destinations(source: &ConfigSource) -> Result<Vec<Route>, AppError> {
+    let mut destinations = Vec::new();
+
+    if let Some(webhook) = source.get("DISCORD_WEBHOOK_URL").filter(|w| !w.trim().is_empty()) {
+        let discord = destination::parse_discord_webhook_url(webhook.trim())
+            .ok_or_else(|| source.error("DISCORD_WEBHOOK_URL", "DISCORD_WEBHOOK_URL must be a valid Discord webhook URL"))?;
+        destinations.push(Destination::Discord(discord));
+    }
+
+    if let Some(json) = load_json_webhook(source)? {
+        destinations.push(Destination::Json(json));
+    }
+
+    if let Some(urls) = source.get("NOTIFY_URLS") {
+        destinations.extend(destination::parse_destination_list(&urls, &source.describe("NOTIFY_URLS"))?);
+    }
+
+    // Quiet hours from the environment apply to every destination without its own
+    let quiet_digest = source.get("QUIET_DIGEST").is_some_and(|v| v.trim().eq_ignore_ascii_case("true"));
+    let quiet_timezone = source.get("QUIET_HOURS_TZ");
+    let quiet_value = source.get("QUIET_HOURS").filter(|v| !v.trim().is_empty());
+    let quiet_hours = quiet_value.as_ref()
+        .map(|value| destination::parse_quiet_hours(value, quiet_timezone.as_deref(), quiet_digest)
+            .map_err(|reason| source.error("QUIET_HOURS", &format!("Invalid QUIET_HOURS: {reason}"))))
+        .transpose()?;
+
+    let mut routes: Vec<Route> = destinations.into_iter()
+        .map(|destination| Route { quiet_hours: quiet_hours.clone(), ..Route::new(destination) })
+        .collect();
+
+    for table in source.destination_tables() {
+        let events = table.events.as_ref()
+            .map(|names| names.iter().map(|name| destination::parse_event_kind(name)).collect::<Result<Vec<_>, _>>())
+            .transpose()
+            .map_err(|reason| AppError::Config(format!("Invalid events in {}: {reason}", table.location)))?;
+        let progress = table.progress.iter().flatten()
+            .map(|name| destination::parse_progress(name))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|reason| AppError::Config(format!("Invalid progress in {}: {reason}", table.location)))?;
+        // A table inherits the environment's quiet hours; an empty `quiet_hours` turns them off
+        let table_quiet_hours = match table.quiet_hours.as_deref().or(quiet_value.as_deref()).map(str::trim) {
+            Some("") | None if table.quiet_hours_tz.is_some() || table.quiet_digest.is_some() => {
+                return Err(AppError::Config(format!(
+                    "quiet_hours_tz and quiet_digest in {} need quiet_hours or QUIET_HOURS", table.location
+                )));
+            }
+            Some("") | None => None,
+            Some(value) => Some(destination::parse_quiet_hours(
+                value,
+                table.quiet_hours_tz.as_deref().or(quiet_timezone.as_deref()),
+                table.quiet_digest.unwrap_or(quiet_digest),
+            ).map_err(|reason| AppError::Config(format!("Invalid quiet_hours in {}: {reason}", table.location)))?),
+        };
+        routes.push(Route {
+            destination: destination::parse_destination_url(&table.url, &table.location)?,
+            events,
+            regions: table.regions.clone().unwrap_or_default(),
+            progress,
+            quiet_hours: table_quiet_hours,
+        });
+    }
+
+    Ok(routes)
+}
+
+/// Load the optional generic JSON webhook from `JSON_WEBHOOK_*` variables
+fn load_json_webhook(source: &ConfigSource) -> Result<Option<JsonWebhookConfig>, AppError> {
+    let Some(url) = source.get("JSON_WEBHOOK_URL").filter(|u| !u.trim().is_empty()) else {
+        return Ok(None);
+    };
+
+    if !url.starts_with("https://") && !url.starts_with("http://") {
+        return Err(source.error("JSON_WEBHOOK_URL", "JSON_WEBHOOK_URL must start with http:// or https://"));
+    }
+
+    let secret = source.get("JSON_WEBHOOK_SECRET").filter(|s| !s.is_empty()).map(Secret::new);
+
+    let max_retries = match source.get("JSON_WEBHOOK_MAX_RETRIES") {
+        Some(value) => value.trim().parse::<u32>()
+            .map_err(|e| source.error("JSON_WEBHOOK_MAX_RETRIES", &format!("Invalid JSON_WEBHOOK_MAX_RETRIES: {e}")))?,
+        None => DEFAULT_JSON_WEBHOOK_MAX_RETRIES,
+    };
+
+    if max_retries > MAX_JSON_WEBHOOK_MAX_RETRIES {
+        return Err(source.error("JSON_WEBHOOK_MAX_RETRIES", &format!(
+            "JSON_WEBHOOK_MAX_RETRIES must be at most {MAX_JSON_WEBHOOK_MAX_RETRIES}"
+        )));
+    }
+
+    Ok(Some(JsonWebhookConfig { url, secret, max_retries, username: None, password: None }))
+}
+
+
+/// Load the optional MQTT publisher from `MQTT_*` variables
+fn load_mqtt(source: &ConfigSource) -> Result<Option<MqttConfig>, AppError> {
+    let Some(host) = source.get("MQTT_HOST").map(|h| h.trim().to_string()).filter(|h| !h.is_empty()) else {
+        return Ok(None);
+    };
+
+    let tls = source.get("MQTT_TLS")
+        .unwrap_or_else(|| "false".to_string())
+        .trim()
+        .eq_ignore_ascii_case("true");
+
+    let port = match source.get("MQTT_PORT") {
+        Some(value) => value.trim().parse::<u16>()
+            .map_err(|e| source.error("MQTT_PORT", &format!("Invalid MQTT_PORT: {e}")))?,
+        None if tls => DEFAULT_MQTT_TLS_PORT,
+        None => DEFAULT_MQTT_PORT,
+    };
+
+    let qos = match source.get("MQTT_QOS") {
+        Some(value) => value.trim().parse::<u8>()
+            .map_err(|e| source.error("MQTT_QOS", &format!("Invalid MQTT_QOS: {e}")))?,
+        None => DEFAULT_MQTT_QOS,
+    };
+
+    if qos > 2 {
+        return Err(source.error("MQTT_QOS", "MQTT_QOS must be 0, 1 or 2"));
+    }
+
+    let topic_prefix = source.get("MQTT_TOPIC_PREFIX")
+        .unwrap_or_else(|| DEFAULT_MQTT_TOPIC_PREFIX.to_string())
+        .trim()
+        .trim_end_matches('/')
+        .to_string();
+
+    if topic_prefix.is_empty() || topic_prefix.contains(['+', '#']) {
+        return Err(source.error("MQTT_TOPIC_PREFIX", "MQTT_TOPIC_PREFIX must be non-empty and must not contain wildcards"));
+    }
+
+    let ca_file = source.get("MQTT_CA_FILE").filter(|f| !f.trim().is_empty());
+    if ca_file.is_some() && !tls {
+        return Err(source.error("MQTT_CA_FILE", "MQTT_CA_FILE requires MQTT_TLS=true"));
+    }
+    if let Some(path) = &ca_file {
+        if !std::path::Path::new(path).is_file() {
+            return Err(source.error("MQTT_CA_FILE", &format!("MQTT_CA_FILE '{path}' does not exist")));
+        }
+    }
+
+    Ok(Some(MqttConfig {
+        host,
+        port,
+        client_id: source.get("MQTT_CLIENT_ID").unwrap_or_else(|| DEFAULT_MQTT_CLIENT_ID.to_string()),
+        username: source.get("MQTT_USERNAME").filter(|u| !u.is_empty()),
+        password: source.get("MQTT_PASSWORD").filter(|p| !p.is_empty()).map(Secret::new),
+        topic_prefix,
+        qos,
+        tls,
+        ca_file,
+    }))
+}
+
+/// Load the log format and file from `LOG_*` variables
+fn load_logging(source: &ConfigSource) -> Result<LoggingConfig, AppError> {
+    let format = match source.get("LOG_FORMAT").map(|f| f.trim().to_ascii_lowercase()).as_deref() {
+        None | Some("" | "text") => LogFormat::Text,
+        Some("json") => LogFormat::Json,
+        Some(other) => return Err(source.error("LOG_FORMAT", &format!("Invalid LOG_FORMAT '{other}' (expected text or json)"))),
+    };
+    let file = source.get("LOG_FILE").filter(|p| !p.trim().is_empty()).map(PathBuf::from);
+    let rotation = match source.get("LOG_ROTATION").filter(|r| !r.trim().is_empty()) {
+        Some(value) => parse_log_rotation(&value).ok_or_else(|| source.error("LOG_ROTATION", &format!(
+            "Invalid LOG_ROTATION '{value}' (expected daily, hourly, never or a size such as 10MB)"
+        )))?,
+        None => LogRotation::default(),
+    };
+    let max_files = match source.get("LOG_MAX_FILES").filter(|v| !v.trim().is_empty()) {
+        Some(value) => value.trim().parse::<usize>()
+            .map_err(|e| source.error("LOG_MAX_FILES", &format!("Invalid LOG_MAX_FILES '{value}': {e}")))?,
+        None => DEFAULT_LOG_MAX_FILES,
+    };
+    Ok(LoggingConfig { format, file, rotation, max_files })
+}
+
+/// `daily`, `hourly`, `never`, or a size in bytes with an optional `KB`, `MB` or `GB` suffix
+fn parse_log_rotation(value: &str) -> Option<LogRotation> {
+    let value = value.trim().to_ascii_uppercase();
+    match value.as_str() {
+        "DAILY" => return Some(LogRotation::Daily),
+        "HOURLY" => return Some(LogRotation::Hourly),
+        "NEVER" => return Some(LogRotation::Never),
+        _ => {}
+    }
+    let digits = value.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let multiplier: u64 = match value[digits.len()..].trim_end_matches('B') {
+        "" => 1,
+        "K" => 1024,
+        "M" => 1024 * 1024,
+        "G" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    digits.trim().parse::<u64>().ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .filter(|bytes| *bytes > 0)
+        .map(LogRotation::Size)
+}
+
+/// Load the `/readyz` thresholds from `READY_*` variables
+fn load_readiness(source: &ConfigSource) -> Result<ReadinessConfig, AppError> {
+    let poll_intervals = match source.get("READY_POLL_INTERVALS").filter(|v| !v.trim().is_empty()) {
+        Some(value) => value.trim().parse::<u32>().ok().filter(|n| *n > 0).ok_or_else(|| {
+            source.error("READY_POLL_INTERVALS", &format!("Invalid READY_POLL_INTERVALS '{value}' (expected a positive number)"))
+        })?,
+        None => DEFAULT_READY_POLL_INTERVALS,
+    };
+    let max_pending = match source.get("READY_MAX_PENDING").filter(|v| !v.trim().is_empty()) {
+        Some(value) => value.trim().parse::<usize>()
+            .map_err(|e| source.error("READY_MAX_PENDING", &format!("Invalid READY_MAX_PENDING '{value}': {e}")))?,
+        None => DEFAULT_READY_MAX_PENDING,
+    };
+    Ok(ReadinessConfig { poll_intervals, max_pending })
+}
+
+/// Load one `ALERT_*` threshold; 0 disables the alert
+fn load_alert_threshold(source: &ConfigSource, name: &str, default: u32) -> Result<u32, AppError> {
+    match source.get(name).filter(|v| !v.trim().is_empty()) {
+        Some(value) => value.trim().parse::<u32>()
+            .map_err(|e| source.error(name, &format!("Invalid {name} '{value}': {e}"))),
+        None => Ok(default),
+    }
+}
+
+/// Load the optional digest from `DIGEST_*` variables; it is enabled by `DIGEST_URLS`
+fn load_digest(source: &ConfigSource) -> Result<Option<DigestConfig>, AppError> {
+    let Some(urls) = source.get("DIGEST_URLS").filter(|v| !v.trim().is_empty()) else {
+        if let Some(name) = ["DIGEST_SCHEDULE", "DIGEST_SCHEDULE_TZ", "DIGEST_ENDING_HOURS"]
+            .into_iter()
+            .find(|name| source.get(name).is_some_and(|v| !v.trim().is_empty()))
+        {
+            return Err(source.error(name, &format!("{name} needs DIGEST_URLS")));
+        }
+        return Ok(None);
+    };
+    let destinations = destination::parse_destination_list(&urls, &source.describe("DIGEST_URLS"))?;
+    let schedule = polling::load_cron_schedule(source, "DIGEST_SCHEDULE", Some(DEFAULT_DIGEST_SCHEDULE))?
+        .ok_or_else(|| source.error("DIGEST_SCHEDULE", "DIGEST_SCHEDULE has no cron expression"))?;
+    let ending_hours = match source.get("DIGEST_ENDING_HOURS").filter(|v| !v.trim().is_empty()) {
+        Some(value) => value.trim().parse::<u32>().ok()
+            .filter(|hours| (1..=MAX_DIGEST_ENDING_HOURS).contains(hours))
+            .ok_or_else(|| source.error("DIGEST_ENDING_HOURS", &format!(
+                "Invalid DIGEST_ENDING_HOURS '{value}' (expected 1 to {MAX_DIGEST_ENDING_HOURS})"
+            )))?,
+        None => DEFAULT_DIGEST_ENDING_HOURS,
+    };
+    Ok(Some(DigestConfig { destinations, schedule, ending_hours }))
+}
+
+/// Load the optional iCalendar export from `CALENDAR_*` variables
+fn load_calendar(source: &ConfigSource) -> Result<CalendarConfig, AppError> {
+    let path = source.get("CALENDAR_PATH").filter(|p| !p.trim().is_empty());
+
+    let all_day = source.get("CALENDAR_ALL_DAY")
+        .unwrap_or_else(|| "false".to_string())
+        .trim()
+        .eq_ignore_ascii_case("true");
+
+    let reminder_hours = source.get("CALENDAR_REMINDER_HOURS")
+        .unwrap_or_else(|| DEFAULT_CALENDAR_REMINDER_HOURS.to_string())
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            let hours = s.parse::<u32>()
+                .map_err(|e| source.error("CALENDAR_REMINDER_HOURS", &format!("Invalid CALENDAR_REMINDER_HOURS entry '{s}': {e}")))?;
+            if hours == 0 || hours > MAX_CALENDAR_REMINDER_HOURS {
+                return Err(source.error("CALENDAR_REMINDER_HOURS", &format!(
+                    "CALENDAR_REMINDER_HOURS entries must be between 1 and {MAX_CALENDAR_REMINDER_HOURS}"
+                )));
+            }
+            Ok(hours)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(CalendarConfig { path, all_day, reminder_hours })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_rotation_accepts_periods_and_sizes() {
+        assert_eq!(parse_log_rotation("Daily"), Some(LogRotation::Daily));
+        assert_eq!(parse_log_rotation(" never "), Some(LogRotation::Never));
+        assert_eq!(parse_log_rotation("512"), Some(LogRotation::Size(512)));
+        assert_eq!(parse_log_rotation("10MB"), Some(LogRotation::Size(10 * 1024 * 1024)));
+        assert_eq!(parse_log_rotation("2 k"), Some(LogRotation::Size(2048)));
+        assert_eq!(parse_log_rotation("1gb"), Some(LogRotation::Size(1024 * 1024 * 1024)));
+        assert_eq!(parse_log_rotation("0MB"), None);
+        assert_eq!(parse_log_rotation("10TB"), None);
+        assert_eq!(parse_log_rotation("weekly"), None);
+    }
+
+    #[test]
+    fn first_digest_covers_the_period_since_the_previous_scheduled_time() {
+        use chrono::TimeZone;
+        let digest = DigestConfig {
+            destinations: Vec::new(),
+            schedule: CronSchedule {
+                expressions: vec!["0 0 9 * * Mon".parse().expect("valid cron expression")],
+                timezone: chrono_tz::Tz::UTC,
+                longest_gap_sec: 0,
+            },
+            ending_hours: DEFAULT_DIGEST_ENDING_HOURS,
+        };
+        let now = Utc.with_ymd_and_hms(2026, 10, 12, 9, 0, 0).unwrap();
+        assert_eq!(digest.period_start(None, now), now - TimeDelta::days(7));
+        let last = now - TimeDelta::days(3);
+        assert_eq!(digest.period_start(Some(last), now), last);
+    }
+}
@@ -0,0 +1,308 @@
+use super::source::ConfigSource;
+use super::{MAX_POLLING_INTERVAL_SEC, MIN_POLLING_INTERVAL_SEC};
+use crate::types::error::AppError;
+use chrono::{DateTime, Datelike, NaiveTime, TimeDelta, Utc, Weekday};
+use chrono_tz::Tz;
+use cron::Schedule;
+use std::str::FromStr;
+
+const DEFAULT_POLL_JITTER_PERCENT: u8 = 10;
+const MAX_POLL_JITTER_PERCENT: u8 = 50;
+const DEFAULT_POLL_BACKOFF_MAX_SEC: u64 = 3600;
+const DEFAULT_LAUNCH_POLLING_INTERVAL_SEC: u64 = 60;
+/// How far ahead a cron schedule is checked for polls that are too close together
+const SCHEDULE_CHECK_DAYS: i64 = 8;
+/// Most upcoming polls checked, so an expression that fires every second cannot stall loading
+const SCHEDULE_CHECK_MAX_FIRES: usize = 50_000;
+
+/// How the time between polls varies around `POLLING_INTERVAL_SEC`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PollingConfig {
+    /// Each wait is randomly lengthened or shortened by up to this percentage
+    pub jitter_percent: u8,
+    /// Longest wait while backing off after failed polls
+    pub backoff_max_sec: u64,
+    /// UTC windows in which quests are usually released and polling is faster
+    pub launch_windows: Vec<LaunchWindow>,
+    /// Polling interval inside a launch window
+    pub launch_interval_sec: u64,
+    /// Cron expressions that replace `POLLING_INTERVAL_SEC` and the launch windows
+    pub schedule: Option<CronSchedule>,
+}
+
+impl PollingConfig {
+    /// Longest regular wait between two polls, e.g. for health checks
+    #[must_use]
+    pub fn longest_wait_sec(&self, interval_sec: u64) -> u64 {
+        self.schedule.as_ref().map_or(interval_sec, |schedule| schedule.longest_gap_sec)
+    }
+}
+
+/// Cron expressions, evaluated in a timezone; a poll runs whenever any of them fires
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CronSchedule {
+    pub expressions: Vec<Schedule>,
+    pub timezone: Tz,
+    /// Longest gap between polls over the next week
+    pub longest_gap_sec: u64,
+}
+
+impl CronSchedule {
+    /// The first time after `now` at which an expression fires
+    #[must_use]
+    pub fn next_after(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let local = now.with_timezone(&self.timezone);
+        self.expressions.iter()
+            .filter_map(|expression| expression.after(&local).next())
+            .min()
+            .map(|next| next.with_timezone(&Utc))
+    }
+
+    /// The last time before `now` at which an expression fired
+    #[must_use]
+    pub fn previous_before(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let local = now.with_timezone(&self.timezone);
+        self.expressions.iter()
+            .filter_map(|expression| expression.after(&local).next_back())
+            .max()
+            .map(|previous| previous.with_timezone(&Utc))
+    }
+}
+
+/// A daily UTC time range, optionally limited to a range of weekdays, e.g. `Mon-Fri 15:00-19:00`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LaunchWindow {
+    /// First and last weekday, counted from the day the window starts
+    pub days: Option<(Weekday, Weekday)>,
+    pub start: NaiveTime,
+    /// Ends at or before `start` for a window that crosses midnight
+    pub end: NaiveTime,
+}
+
+impl LaunchWindow {
+    /// Whether `now` falls inside this window
+    #[must_use]
+    pub fn contains(&self, now: DateTime<Utc>) -> bool {
+        let time = now.time();
+        let (in_range, start_day) = if self.start < self.end {
+            (self.start <= time && time < self.end, now.weekday())
+        } else if time >= self.start {
+            (true, now.weekday())
+        } else {
+            // After midnight, the window started the previous day
+            (time < self.end, now.weekday().pred())
+        };
+        in_range && self.days.is_none_or(|(first, last)| weekday_in(start_day, first, last))
+    }
+}
+
+/// Whether `day` lies in `first..=last`, wrapping around the week (e.g. `Sat-Mon`)
+fn weekday_in(day: Weekday, first: Weekday, last: Weekday) -> bool {
+    let offset = |d: Weekday| (d.num_days_from_monday() + 7 - first.num_days_from_monday()) % 7;
+    offset(day) <= offset(last)
+}
+
+/// Load the jitter, back-off and launch window settings from `POLL_*` and `LAUNCH_*` variables
+pub(super) fn load_polling(source: &ConfigSource) -> Result<PollingConfig, AppError> {
+    let jitter_percent = match source.get("POLL_JITTER_PERCENT").filter(|v| !v.trim().is_empty()) {
+        Some(value) => value.trim().parse::<u8>().ok().filter(|p| *p <= MAX_POLL_JITTER_PERCENT).ok_or_else(|| {
+            source.error("POLL_JITTER_PERCENT", &format!(
+                "Invalid POLL_JITTER_PERCENT '{value}' (expected 0 to {MAX_POLL_JITTER_PERCENT})"
+            ))
+        })?,
+        None => DEFAULT_POLL_JITTER_PERCENT,
+    };
+    let backoff_max_sec = load_interval(source, "POLL_BACKOFF_MAX_SEC", DEFAULT_POLL_BACKOFF_MAX_SEC)?;
+    let launch_interval_sec = load_interval(source, "LAUNCH_POLLING_INTERVAL_SEC", DEFAULT_LAUNCH_POLLING_INTERVAL_SEC)?;
+    let launch_windows = match source.get("LAUNCH_WINDOWS") {
+        Some(value) => value.split(',')
+            .map(str::trim)
+            .filter(|w| !w.is_empty())
+            .map(|w| parse_launch_window(w).ok_or_else(|| source.error("LAUNCH_WINDOWS", &format!(
+                "Invalid launch window '{w}' (expected e.g. 15:00-19:00 or Mon-Fri 15:00-19:00, in UTC)"
+            ))))
+            .collect::<Result<Vec<_>, _>>()?,
+        None => Vec::new(),
+    };
+    let schedule = load_cron_schedule(source, "POLL_SCHEDULE", None)?;
+    if schedule.is_some() && !launch_windows.is_empty() {
+        return Err(source.error("LAUNCH_WINDOWS", "LAUNCH_WINDOWS cannot be combined with POLL_SCHEDULE; add the windows to the schedule instead"));
+    }
+    Ok(PollingConfig { jitter_percent, backoff_max_sec, launch_windows, launch_interval_sec, schedule })
+}
+
+/// Load semicolon-separated cron expressions from `name` and their timezone from
+/// `<name>_TZ`, falling back to `default` expressions when the setting is empty
+pub(super) fn load_cron_schedule(
+    source: &ConfigSource,
+    name: &str,
+    default: Option<&str>,
+) -> Result<Option<CronSchedule>, AppError> {
+    let tz_name = format!("{name}_TZ");
+    let tz_value = source.get(&tz_name).filter(|v| !v.trim().is_empty());
+    let Some(value) = source.get(name).filter(|v| !v.trim().is_empty()).or_else(|| default.map(str::to_string)) else {
+        if tz_value.is_some() {
+            return Err(source.error(&tz_name, &format!("{tz_name} needs {name}")));
+        }
+        return Ok(None);
+    };
+    let timezone = match tz_value {
+        Some(value) => Tz::from_str(value.trim()).map_err(|_| source.error(&tz_name, &format!(
+            "Invalid {tz_name} '{value}' (expected an IANA timezone such as Europe/Berlin)"
+        )))?,
+        None => Tz::UTC,
+    };
+    let expressions = value.split(';')
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+        .map(|e| parse_cron(e).map_err(|message| source.error(name, &format!("Invalid cron expression '{e}': {message}"))))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut schedule = CronSchedule { expressions, timezone, longest_gap_sec: 0 };
+    schedule.longest_gap_sec = check_gaps(&schedule, Utc::now())
+        .map_err(|message| source.error(name, &format!("Invalid {name}: {message}")))?;
+    Ok(Some(schedule))
+}
+
+/// A 5-field (minute to weekday) or 6- and 7-field (with seconds and year) cron expression
+fn parse_cron(expression: &str) -> Result<Schedule, String> {
+    let expression = if expression.starts_with('@') || expression.split_whitespace().count() != 5 {
+        expression.to_string()
+    } else {
+        format!("0 {expression}")
+    };
+    Schedule::from_str(&expression).map_err(|e| e.to_string())
+}
+
+/// Check that the schedule fires and never runs polls closer than the minimum interval
+/// over the next days, returning the longest gap between polls in seconds
+fn check_gaps(schedule: &CronSchedule, now: DateTime<Utc>) -> Result<u64, String> {
+    let end = now + TimeDelta::days(SCHEDULE_CHECK_DAYS);
+    let mut previous = schedule.next_after(now).ok_or("the schedule never fires")?;
+    let mut longest = 0;
+    for _ in 0..SCHEDULE_CHECK_MAX_FIRES {
+        let Some(next) = schedule.next_after(previous) else { break };
+        let gap = u64::try_from((next - previous).num_seconds()).unwrap_or(0);
+        if gap < MIN_POLLING_INTERVAL_SEC {
+            return Err(format!(
+                "polls at {previous} and {next} are less than {MIN_POLLING_INTERVAL_SEC} seconds apart"
+            ));
+        }
+        longest = longest.max(gap);
+        if next > end {
+            break;
+        }
+        previous = next;
+    }
+    Ok(longest.min(MAX_POLLING_INTERVAL_SEC))
+}
+
+/// Load an interval in seconds, held to the same bounds as `POLLING_INTERVAL_SEC`
+fn load_interval(source: &ConfigSource, name: &str, default: u64) -> Result<u64, AppError> {
+    let Some(value) = source.get(name).filter(|v| !v.trim().is_empty()) else { return Ok(default) };
+    value.trim().parse::<u64>().ok()
+        .filter(|secs| (MIN_POLLING_INTERVAL_SEC..=MAX_POLLING_INTERVAL_SEC).contains(secs))
+        .ok_or_else(|| source.error(name, &format!(
+            "Invalid {name} '{value}' (expected {MIN_POLLING_INTERVAL_SEC} to {MAX_POLLING_INTERVAL_SEC} seconds)"
+        )))
+}
+
+/// `HH:MM-HH:MM`, optionally preceded by a weekday or weekday range such as `Mon-Fri`
+fn parse_launch_window(value: &str) -> Option<LaunchWindow> {
+    let (days, times) = match value.split_once(char::is_whitespace) {
+        Some((days, times)) => {
+            let days = match days.split_once('-') {
+                Some((first, last)) => (first.parse().ok()?, last.parse().ok()?),
+                None => {
+                    let day = days.parse().ok()?;
+                    (day, day)
+                }
+            };
+            (Some(days), times.trim())
+        }
+        None => (None, value),
+    };
+    let (start, end) = times.split_once('-')?;
+    let parse_time = |t: &str| NaiveTime::parse_from_str(t.trim(), "%H:%M").ok();
+    let (start, end) = (parse_time(start)?, parse_time(end)?);
+    (start != end).then_some(LaunchWindow { days, start, end })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::source::load_config;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        // 2026-10-12 is a Monday
+        Utc.with_ymd_and_hms(2026, 10, 11 + day, hour, minute, 0).unwrap()
+    }
+
+    fn cron_schedule(expressions: &[&str], timezone: Tz) -> CronSchedule {
+        let expressions = expressions.iter().map(|e| parse_cron(e).unwrap()).collect();
+        CronSchedule { expressions, timezone, longest_gap_sec: 0 }
+    }
+
+    #[test]
+    fn cron_schedule_combines_expressions_in_its_timezone() {
+        // Every minute on weekdays 15:00-19:00, every 15 minutes otherwise
+        let schedule = cron_schedule(&["* 15-18 * * Mon-Fri", "*/15 * * * *"], Tz::UTC);
+        assert_eq!(schedule.next_after(at(1, 15, 0)), Some(at(1, 15, 1)));
+        assert_eq!(schedule.next_after(at(1, 19, 0)), Some(at(1, 19, 15)));
+        assert_eq!(schedule.next_after(at(6, 16, 0)), Some(at(6, 16, 15)), "Saturday");
+        assert_eq!(check_gaps(&schedule, at(1, 0, 0)), Ok(900));
+
+        let berlin = cron_schedule(&["0 9 * * *"], Tz::Europe__Berlin);
+        assert_eq!(berlin.next_after(at(1, 0, 0)), Some(at(1, 7, 0)), "09:00 CEST is 07:00 UTC");
+        assert_eq!(berlin.previous_before(at(1, 7, 0)), Some(at(0, 7, 0)));
+        assert_eq!(schedule.previous_before(at(1, 15, 30)), Some(at(1, 15, 29)));
+    }
+
+    #[test]
+    fn cron_schedule_rejects_invalid_and_too_frequent_expressions() {
+        assert!(parse_cron("61 * * * *").is_err());
+        assert!(parse_cron("@hourly").is_ok());
+        assert!(check_gaps(&cron_schedule(&["*/10 * * * * *"], Tz::UTC), at(1, 0, 0)).is_err());
+        assert!(check_gaps(&cron_schedule(&["0 * * * *", "10 * * * * *"], Tz::UTC), at(1, 0, 0)).is_err());
+        assert!(check_gaps(&cron_schedule(&["0 0 0 1 1 * 2020"], Tz::UTC), at(1, 0, 0)).is_err());
+    }
+
+    #[test]
+    fn schedule_timezone_needs_a_schedule() {
+        let source = load_config("schedule-tz", "[default]\npoll_schedule_tz = \"Europe/Berlin\"\n", None).expect("valid config");
+        let Err(AppError::Config(message)) = load_cron_schedule(&source, "POLL_SCHEDULE", None) else {
+            panic!("expected a configuration error");
+        };
+        assert!(message.contains("POLL_SCHEDULE_TZ needs POLL_SCHEDULE"), "{message}");
+    }
+
+    #[test]
+    fn launch_windows_parse_with_and_without_days() {
+        let window = parse_launch_window("Mon-Fri 15:00-19:00").unwrap();
+        assert_eq!(window.days, Some((Weekday::Mon, Weekday::Fri)));
+        assert_eq!(parse_launch_window("tue 08:30-09:00").unwrap().days, Some((Weekday::Tue, Weekday::Tue)));
+        assert_eq!(parse_launch_window("15:00-19:00").unwrap().days, None);
+        assert!(parse_launch_window("15:00").is_none());
+        assert!(parse_launch_window("Someday 15:00-19:00").is_none());
+        assert!(parse_launch_window("25:00-26:00").is_none());
+        assert!(parse_launch_window("15:00-15:00").is_none());
+    }
+
+    #[test]
+    fn launch_window_matches_days_and_midnight_crossing() {
+        let weekdays = parse_launch_window("Mon-Fri 15:00-19:00").unwrap();
+        assert!(weekdays.contains(at(1, 15, 0)));
+        assert!(!weekdays.contains(at(1, 19, 0)));
+        assert!(!weekdays.contains(at(6, 16, 0)), "Saturday");
+
+        let night = parse_launch_window("Fri 22:00-02:00").unwrap();
+        assert!(night.contains(at(5, 23, 0)));
+        assert!(night.contains(at(6, 1, 0)), "Saturday morning belongs to Friday's window");
+        assert!(!night.contains(at(5, 1, 0)), "Friday morning belongs to Thursday's window");
+
+        let weekend = parse_launch_window("Sat-Mon 10:00-11:00").unwrap();
+        assert!(weekend.contains(at(7, 10, 30)));
+        assert!(weekend.contains(at(1, 10, 30)));
+        assert!(!weekend.contains(at(2, 10, 30)));
+    }
+}
@@ -0,0 +1,180 @@
+use crate::config::AppConfig;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::{broadcast, mpsc, watch};
+use tracing::{error, info};
+
+const FILE_CHECK_INTERVAL_SECS: u64 = 5;
+
+/// Reload the configuration when the config file changes or a reload is requested
+/// (SIGHUP on Unix), publishing each valid config on `config_tx`
+///
+/// Invalid configs are rejected with an error and the previous config stays active.
+pub async fn watch_config(
+    config_tx: watch::Sender<Arc<AppConfig>>,
+    mut reload_rx: mpsc::Receiver<()>,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    let location = config_tx.borrow().location.clone();
+    let mut last_modified = location.path.as_deref().and_then(modified_time);
+    let mut interval = tokio::time::interval(Duration::from_secs(FILE_CHECK_INTERVAL_SECS));
+
+    if let Some(path) = &location.path {
+        info!("Watching config file {} for changes", path.display());
+    }
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                let Some(path) = &location.path else { continue };
+                let modified = modified_time(path);
+                if modified == last_modified {
+                    continue;
+                }
+                last_modified = modified;
+                info!("Config file {} changed, reloading", path.display());
+            }
+            request = reload_rx.recv() => {
+                if request.is_none() {
+                    return;
+                }
+                info!("Reload requested, reloading configuration");
+            }
+            _ = shutdown_rx.recv() => return,
+        }
+
+        match AppConfig::load(&location) {
+            Ok(mut new_config) => {
+                // Command-line options are not part of the config sources
+                new_config.dry_run.clone_from(&config_tx.borrow().dry_run);
+                let changes = diff(&config_tx.borrow(), &new_config);
+                if changes.is_empty() {
+                    info!("Configuration reloaded, no changes");
+                    continue;
+                }
+                for change in &changes {
+                    info!("Config change: {}", change);
+                }
+                config_tx.send_replace(Arc::new(new_config));
+            }
+            Err(e) => {
+                error!("Rejected configuration reload, keeping previous config: {}", e);
+            }
+        }
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Human-readable list of differences between two configs, with secrets omitted
+#[must_use]
+pub fn diff(old: &AppConfig, new: &AppConfig) -> Vec<String> {
+    let mut changes = Vec::new();
+
+    if old.polling_interval_sec != new.polling_interval_sec {
+        changes.push(format!("polling_interval_sec: {} -> {}", old.polling_interval_sec, new.polling_interval_sec));
+    }
+    if old.polling != new.polling {
+        changes.push(format!(
+            "polling: {}% jitter, back-off up to {}s, {} launch window(s) at {}s, {} cron expression(s)",
+            new.polling.jitter_percent, new.polling.backoff_max_sec,
+            new.polling.launch_windows.len(), new.polling.launch_interval_sec,
+            new.polling.schedule.as_ref().map_or(0, |s| s.expressions.len())
+        ));
+    }
+    for account in &new.accounts {
+        let Some(previous) = old.accounts.iter().find(|a| a.name == account.name) else {
+            changes.push(format!("account added: {}", account.name));
+            continue;
+        };
+        if previous.token != account.token {
+            changes.push(format!("account {}: token changed", account.name));
+        }
+        if previous.super_properties != account.super_properties {
+            changes.push(format!("account {}: super_properties changed", account.name));
+        }
+        if previous.regions != account.regions {
+            changes.push(format!("account {}: regions {:?} -> {:?}", account.name, previous.regions, account.regions));
+        }
+    }
+    for account in &old.accounts {
+        if !new.accounts.iter().any(|a| a.name == account.name) {
+            changes.push(format!("account removed: {}", account.name));
+        }
+    }
+    if old.previous_quests != new.previous_quests {
+        changes.push(format!("previous_quest: {} -> {}", old.previous_quests, new.previous_quests));
+    }
+
+    for destination in &new.destinations {
+        let label = destination.label();
+        match old.destinations.iter().find(|d| d.label() == label) {
+            None => changes.push(format!("destination added: {label}")),
+            Some(previous) if previous != destination => changes.push(format!("destination changed: {label}")),
+            Some(_) => {}
+        }
+    }
+    for destination in &old.destinations {
+        let label = destination.label();
+        if !new.destinations.iter().any(|d| d.label() == label) {
+            changes.push(format!("destination removed: {label}"));
+        }
+    }
+
+    if old.ops_destinations != new.ops_destinations {
+        let labels: Vec<String> = new.ops_destinations.iter().map(|d| d.label()).collect();
+        changes.push(format!("ops destinations: {labels:?}"));
+    }
+    if old.mqtt != new.mqtt {
+        changes.push("mqtt: changed (reconnecting)".to_string());
+    }
+    if old.feed_atom_path != new.feed_atom_path {
+        changes.push(format!("feed_atom_path: {:?} -> {:?}", old.feed_atom_path, new.feed_atom_path));
+    }
+    if old.feed_rss_path != new.feed_rss_path {
+        changes.push(format!("feed_rss_path: {:?} -> {:?}", old.feed_rss_path, new.feed_rss_path));
+    }
+    if old.calendar != new.calendar {
+        changes.push("calendar: changed".to_string());
+    }
+    if old.state_file != new.state_file {
+        changes.push(format!("state_file: {:?} -> {:?}", old.state_file, new.state_file));
+    }
+    if old.readiness != new.readiness {
+        changes.push(format!(
+            "readiness: {} polling intervals, {} pending deliveries",
+            new.readiness.poll_intervals, new.readiness.max_pending
+        ));
+    }
+    if old.alerts != new.alerts {
+        changes.push(format!(
+            "alerts: after {} failed fetches, {} failed deliveries",
+            new.alerts.fetch_failures, new.alerts.delivery_failures
+        ));
+    }
+    if old.digest != new.digest {
+        changes.push(match &new.digest {
+            Some(digest) => format!(
+                "digest: {} ({}) to {} destination(s)",
+                digest.schedule.expressions.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "),
+                digest.schedule.timezone,
+                digest.destinations.len()
+            ),
+            None => "digest: disabled".to_string(),
+        });
+    }
+    if old.shutdown_timeout_sec != new.shutdown_timeout_sec {
+        changes.push(format!("shutdown_timeout_sec: {} -> {}", old.shutdown_timeout_sec, new.shutdown_timeout_sec));
+    }
+    if old.logging != new.logging {
+        changes.push("logging: changed (takes effect after a restart)".to_string());
+    }
+    if old.http_addr != new.http_addr {
+        changes.push(format!("http_addr: {:?} -> {:?} (takes effect after a restart)", old.http_addr, new.http_addr));
+    }
+
+    changes
+}
@@ -0,0 +1,576 @@
+use crate::types::error::AppError;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::path::PathBuf;
+use toml::Spanned;
+
+/// Every setting readable from the environment or a config file.
+/// In the file, a setting uses its lowercase name (e.g. `polling_interval_sec`).
+pub const SETTINGS: &[&str] = &[
+    "DISCORD_AUTH_TOKEN",
+    "DISCORD_WEBHOOK_URL",
+    "NOTIFY_URLS",
+    "OPS_NOTIFY_URLS",
+    "POLLING_INTERVAL_SEC",
+    "DISCORD_REGIONS",
+    "PREVIOUS_QUEST",
+    "SUPER_PROPERTIES",
+    "JSON_WEBHOOK_URL",
+    "JSON_WEBHOOK_SECRET",
+    "JSON_WEBHOOK_MAX_RETRIES",
+    "MQTT_HOST",
+    "MQTT_PORT",
+    "MQTT_USERNAME",
+    "MQTT_PASSWORD",
+    "MQTT_CLIENT_ID",
+    "MQTT_TOPIC_PREFIX",
+    "MQTT_QOS",
+    "MQTT_TLS",
+    "MQTT_CA_FILE",
+    "FEED_ATOM_PATH",
+    "FEED_RSS_PATH",
+    "CALENDAR_PATH",
+    "CALENDAR_ALL_DAY",
+    "CALENDAR_REMINDER_HOURS",
+    "STATE_FILE",
+    "PREFLIGHT",
+    "HTTP_ADDR",
+    "READY_POLL_INTERVALS",
+    "READY_MAX_PENDING",
+    "LOG_FORMAT",
+    "LOG_FILE",
+    "LOG_ROTATION",
+    "LOG_MAX_FILES",
+    "ALERT_FETCH_FAILURES",
+    "ALERT_DELIVERY_FAILURES",
+    "POLL_JITTER_PERCENT",
+    "POLL_BACKOFF_MAX_SEC",
+    "LAUNCH_WINDOWS",
+    "LAUNCH_POLLING_INTERVAL_SEC",
+    "POLL_SCHEDULE",
+    "POLL_SCHEDULE_TZ",
+    "QUIET_HOURS",
+    "QUIET_HOURS_TZ",
+    "QUIET_DIGEST",
+    "DIGEST_URLS",
+    "DIGEST_SCHEDULE",
+    "DIGEST_SCHEDULE_TZ",
+    "DIGEST_ENDING_HOURS",
+    "SHUTDOWN_TIMEOUT_SEC",
+];
+
+/// Settings that can instead be read from the file named by `<NAME>_FILE`
+/// (e.g. Docker or Kubernetes secrets). Trailing newlines in the file are ignored.
+pub const FILE_SETTINGS: &[&str] = &[
+    "DISCORD_AUTH_TOKEN",
+    "DISCORD_WEBHOOK_URL",
+    "NOTIFY_URLS",
+    "OPS_NOTIFY_URLS",
+    "DIGEST_URLS",
+    "JSON_WEBHOOK_URL",
+    "JSON_WEBHOOK_SECRET",
+    "MQTT_PASSWORD",
+];
+const FILE_SUFFIX: &str = "_FILE";
+
+/// Settings whose array values are joined with spaces instead of commas
+const SPACE_SEPARATED_SETTINGS: &[&str] = &["NOTIFY_URLS", "OPS_NOTIFY_URLS", "DIGEST_URLS"];
+/// Settings whose array values are joined with semicolons, because the values contain commas
+const SEMICOLON_SEPARATED_SETTINGS: &[&str] = &["POLL_SCHEDULE", "DIGEST_SCHEDULE"];
+
+/// File-only key holding `[[destination]]` tables
+const DESTINATION_TABLE_KEY: &str = "destination";
+/// File-only key holding `[[account]]` tables
+const ACCOUNT_TABLE_KEY: &str = "account";
+
+/// Which config file to read and which profile to apply on top of `[default]`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConfigLocation {
+    pub path: Option<PathBuf>,
+    pub profile: Option<String>,
+}
+
+impl ConfigLocation {
+    /// Fill fields not given on the command line from `QUEST_CONFIG` / `QUEST_PROFILE`
+    #[must_use]
+    pub fn with_env_defaults(self) -> Self {
+        Self {
+            path: self.path.or_else(|| env::var("QUEST_CONFIG").ok().filter(|p| !p.is_empty()).map(PathBuf::from)),
+            profile: self.profile.or_else(|| env::var("QUEST_PROFILE").ok().filter(|p| !p.is_empty())),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileDocument {
+    #[serde(default)]
+    default: FileTable,
+    #[serde(default)]
+    profile: BTreeMap<String, FileTable>,
+}
+
+/// Settings of `[default]` or a profile, with the locations of keys and values
+type FileTable = BTreeMap<Spanned<String>, Spanned<toml::Value>>;
+
+struct FileValue {
+    value: String,
+    line: usize,
+    column: usize,
+}
+
+/// Options of a `[[destination]]` table, which env vars cannot express
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileDestination {
+    url: String,
+    events: Option<Vec<String>>,
+    regions: Option<Vec<String>>,
+    progress: Option<Vec<String>>,
+    quiet_hours: Option<String>,
+    quiet_hours_tz: Option<String>,
+    quiet_digest: Option<bool>,
+}
+
+/// A Discord account from an `[[account]]` table
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileAccount {
+    name: String,
+    token: Option<String>,
+    token_file: Option<String>,
+    super_properties: Option<String>,
+    regions: Option<Vec<String>>,
+}
+
+/// An `[[account]]` table from the config file, with `token_file` already read
+pub struct AccountTable {
+    pub name: String,
+    pub token: Option<String>,
+    pub super_properties: Option<String>,
+    pub regions: Option<Vec<String>>,
+    /// Where the table is defined, e.g. `account 'alt' (quests.toml:20:1)`
+    pub location: String,
+}
+
+/// A `[[destination]]` table from the config file
+pub struct DestinationTable {
+    pub url: String,
+    pub events: Option<Vec<String>>,
+    pub regions: Option<Vec<String>>,
+    pub progress: Option<Vec<String>>,
+    pub quiet_hours: Option<String>,
+    pub quiet_hours_tz: Option<String>,
+    pub quiet_digest: Option<bool>,
+    /// Where the table is defined, e.g. `destination #2 (quests.toml:12:1)`
+    pub location: String,
+}
+
+/// Layered settings: environment variables override the selected profile,
+/// which overrides `[default]` in the config file
+#[derive(Default)]
+pub struct ConfigSource {
+    file_name: String,
+    values: BTreeMap<String, FileValue>,
+    /// Contents of files named by `<NAME>_FILE` environment variables
+    env_files: BTreeMap<String, String>,
+    destinations: Vec<DestinationTable>,
+    accounts: Vec<AccountTable>,
+}
+
+impl ConfigSource {
+    /// Read the config file (if any), resolve the selected profile and read the files
+    /// named by `<NAME>_FILE` settings
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Config` with the file, line and column if the file cannot be
+    /// parsed, contains an unknown setting or a value of an unsupported type, or if the
+    /// requested profile does not exist. Also fails if a `<NAME>_FILE` cannot be read or
+    /// is set together with `<NAME>` in the same place.
+    pub fn load(location: &ConfigLocation) -> Result<Self, AppError> {
+        let mut source = Self::load_file(location)?;
+        source.read_setting_files()?;
+        Ok(source)
+    }
+
+    fn load_file(location: &ConfigLocation) -> Result<Self, AppError> {
+        let Some(path) = &location.path else {
+            if let Some(profile) = &location.profile {
+                return Err(AppError::Config(format!(
+                    "Profile '{profile}' was requested but no config file was given (use --config or QUEST_CONFIG)"
+                )));
+            }
+            return Ok(Self::default());
+        };
+
+        let file_name = path.display().to_string();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| AppError::Config(format!("Failed to read config file '{file_name}': {e}")))?;
+        let document: FileDocument = toml::from_str(&contents)
+            .map_err(|e| AppError::Config(format!("Invalid config file '{file_name}': {e}")))?;
+
+        let mut source = Self { file_name, ..Self::default() };
+        source.merge(&contents, document.default)?;
+
+        if let Some(profile) = &location.profile {
+            let mut profiles = document.profile;
+            let Some(overrides) = profiles.remove(profile) else {
+                let available: Vec<&String> = profiles.keys().collect();
+                return Err(AppError::Config(format!(
+                    "Profile '{profile}' not found in '{}' (available: {available:?})",
+                    source.file_name
+                )));
+            };
+            source.merge(&contents, overrides)?;
+        }
+
+        Ok(source)
+    }
+
+    fn merge(&mut self, contents: &str, table: FileTable) -> Result<(), AppError> {
+        for key in table.keys() {
+            let base = key.get_ref().strip_suffix("_file").filter(|base| table.contains_key(*base));
+            if base.is_some_and(|base| FILE_SETTINGS.contains(&base.to_ascii_uppercase().as_str())) {
+                let (line, column) = line_column(contents, key.span().start);
+                return Err(AppError::Config(format!(
+                    "Setting '{}' at {}:{line}:{column} conflicts with '{}' in the same table; use only one",
+                    key.get_ref(), self.file_name, base.unwrap_or_default()
+                )));
+            }
+        }
+
+        for (key, spanned) in table {
+            let (key_line, key_column) = line_column(contents, key.span().start);
+            let key = key.into_inner();
+            let (line, column) = line_column(contents, spanned.span().start);
+            if key == DESTINATION_TABLE_KEY {
+                // A profile's destination list replaces the one from [default]
+                self.destinations = self.parse_destination_tables(spanned.into_inner(), line, column)?;
+                continue;
+            }
+            if key == ACCOUNT_TABLE_KEY {
+                // Like destinations, a profile's accounts replace those from [default]
+                self.accounts = self.parse_account_tables(spanned.into_inner(), line, column)?;
+                continue;
+            }
+            let name = key.to_ascii_uppercase();
+            if !SETTINGS.contains(&name.as_str()) && file_setting_base(&name).is_none() {
+                return Err(AppError::Config(format!(
+                    "Unknown setting '{key}' at {}:{key_line}:{key_column}",
+                    self.file_name
+                )));
+            }
+            let separator = if SPACE_SEPARATED_SETTINGS.contains(&name.as_str()) {
+                " "
+            } else if SEMICOLON_SEPARATED_SETTINGS.contains(&name.as_str()) {
+                ";"
+            } else {
+                ","
+            };
+            let value = value_to_string(spanned.get_ref(), separator).ok_or_else(|| {
+                AppError::Config(format!(
+                    "Setting '{key}' at {}:{line}:{column} must be a string, number, boolean or array of those",
+                    self.file_name
+                ))
+            })?;
+            // A profile's `<name>` replaces `<name>_file` from [default] and vice versa
+            match file_setting_base(&name) {
+                Some(base) => { self.values.remove(base); }
+                None => { self.values.remove(&format!("{name}{FILE_SUFFIX}")); }
+            }
+            self.values.insert(name, FileValue { value, line, column });
+        }
+        Ok(())
+    }
+
+    /// Replace `<NAME>_FILE` settings from the environment and the config file by the
+    /// contents of the files they name
+    fn read_setting_files(&mut self) -> Result<(), AppError> {
+        for name in FILE_SETTINGS {
+            let file_setting = format!("{name}{FILE_SUFFIX}");
+
+            if let Some(path) = env::var(&file_setting).ok().filter(|p| !p.is_empty()) {
+                if env::var(name).is_ok() {
+                    return Err(AppError::Config(format!("Both {name} and {file_setting} are set; use only one")));
+                }
+                self.env_files.insert((*name).to_string(), read_setting_file(&file_setting, &path).map_err(AppError::Config)?);
+            }
+
+            if let Some(file_value) = self.values.remove(&file_setting) {
+                let value = read_setting_file(&file_setting, &file_value.value).map_err(|e| AppError::Config(format!(
+                    "{e} (at {}:{}:{})", self.file_name, file_value.line, file_value.column
+                )))?;
+                self.values.insert((*name).to_string(), FileValue { value, ..file_value });
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_destination_tables(&self, value: toml::Value, line: usize, column: usize) -> Result<Vec<DestinationTable>, AppError> {
+        let tables: Vec<FileDestination> = value.try_into().map_err(|e| AppError::Config(format!(
+            "Invalid [[destination]] table at {}:{line}:{column}: {}",
+            self.file_name, e.to_string().trim_end()
+        )))?;
+        Ok(tables.into_iter()
+            .enumerate()
+            .map(|(index, table)| DestinationTable {
+                url: table.url,
+                events: table.events,
+                regions: table.regions,
+                progress: table.progress,
+                quiet_hours: table.quiet_hours,
+                quiet_hours_tz: table.quiet_hours_tz,
+                quiet_digest: table.quiet_digest,
+                location: format!("destination #{} ({}:{line}:{column})", index + 1, self.file_name),
+            })
+            .collect())
+    }
+
+    fn parse_account_tables(&self, value: toml::Value, line: usize, column: usize) -> Result<Vec<AccountTable>, AppError> {
+        let tables: Vec<FileAccount> = value.try_into().map_err(|e| AppError::Config(format!(
+            "Invalid [[account]] table at {}:{line}:{column}: {}",
+            self.file_name, e.to_string().trim_end()
+        )))?;
+        tables.into_iter()
+            .map(|table| {
+                let location = format!("account '{}' ({}:{line}:{column})", table.name, self.file_name);
+                let token = match (table.token, table.token_file) {
+                    (Some(_), Some(_)) => return Err(AppError::Config(format!("{location} sets both token and token_file; use only one"))),
+                    (None, Some(path)) => Some(read_setting_file("token_file", &path)
+                        .map_err(|e| AppError::Config(format!("{e} in {location}")))?),
+                    (token, None) => token,
+                };
+                Ok(AccountTable {
+                    name: table.name,
+                    token,
+                    super_properties: table.super_properties,
+                    regions: table.regions,
+                    location,
+                })
+            })
+            .collect()
+    }
+
+    /// `[[account]]` tables from `[default]`, or from the profile if it defines any
+    #[must_use]
+    pub fn account_tables(&self) -> &[AccountTable] {
+        &self.accounts
+    }
+
+    /// `[[destination]]` tables from `[default]`, or from the profile if it defines any
+    #[must_use]
+    pub fn destination_tables(&self) -> &[DestinationTable] {
+        &self.destinations
+    }
+
+    /// Value of a setting, from the environment first and the config file second
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.env_value(name).or_else(|| self.values.get(name).map(|v| v.value.clone()))
+    }
+
+    /// Value of `<NAME>` or the contents of `<NAME>_FILE` from the environment
+    fn env_value(&self, name: &str) -> Option<String> {
+        env::var(name).ok().or_else(|| self.env_files.get(name).cloned())
+    }
+
+    /// Where the effective value of a setting comes from, e.g. `notify_urls (quests.toml:7:1)`
+    #[must_use]
+    pub fn describe(&self, name: &str) -> String {
+        match self.values.get(name) {
+            Some(value) if self.env_value(name).is_none() => format!(
+                "{} ({}:{}:{})",
+                name.to_ascii_lowercase(), self.file_name, value.line, value.column
+            ),
+            _ => name.to_string(),
+        }
+    }
+
+    /// Build a configuration error, pointing at the file location when the value came from the file
+    #[must_use]
+    pub fn error(&self, name: &str, message: &str) -> AppError {
+        match self.values.get(name) {
+            Some(value) if self.env_value(name).is_none() => AppError::Config(format!(
+                "{message} (at {}:{}:{})",
+                self.file_name, value.line, value.column
+            )),
+            _ => AppError::Config(message.to_string()),
+        }
+    }
+}
+
+/// `DISCORD_AUTH_TOKEN` for `DISCORD_AUTH_TOKEN_FILE`, if the setting supports files
+fn file_setting_base(name: &str) -> Option<&str> {
+    name.strip_suffix(FILE_SUFFIX).filter(|base| FILE_SETTINGS.contains(base))
+}
+
+fn read_setting_file(setting: &str, path: &str) -> Result<String, String> {
+    std::fs::read_to_string(path)
+        .map(|contents| contents.trim_end_matches(['\r', '\n']).to_string())
+        .map_err(|e| format!("Failed to read {setting} '{path}': {e}"))
+}
+
+fn value_to_string(value: &toml::Value, separator: &str) -> Option<String> {
+    match value {
+        toml::Value::String(s) => Some(s.clone()),
+        toml::Value::Integer(i) => Some(i.to_string()),
+        toml::Value::Float(f) => Some(f.to_string()),
+        toml::Value::Boolean(b) => Some(b.to_string()),
+        toml::Value::Array(items) => items.iter()
+            .map(|item| match item {
+                toml::Value::Array(_) | toml::Value::Table(_) => None,
+                scalar => value_to_string(scalar, separator),
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(|items| items.join(separator)),
+        toml::Value::Datetime(_) | toml::Value::Table(_) => None,
+    }
+}
+
+/// 1-based line and column of a byte offset
+fn line_column(contents: &str, offset: usize) -> (usize, usize) {
+    let before = &contents[..offset.min(contents.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rfind('\n').map_or(before.len(), |newline| before.len() - newline - 1) + 1;
+    (line, column)
+}
+
+/// Write `contents` to a config file unique to the calling test and load it
+#[cfg(test)]
+pub(super) fn load_config(test: &str, contents: &str, profile: Option<&str>) -> Result<ConfigSource, AppError> {
+    let path = env::temp_dir().join(format!("quest-config-{}-{test}.toml", std::process::id()));
+    std::fs::write(&path, contents).expect("write test config");
+    let source = ConfigSource::load(&ConfigLocation { path: Some(path.clone()), profile: profile.map(str::to_string) });
+    let _ = std::fs::remove_file(path);
+    source
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str = r#"[default]
+polling_interval_sec = 300
+discord_regions = ["en-US", "de"]
+notify_urls = ["ntfy://a", "ntfy://b"]
+
+[[default.destination]]
+url = "ntfy://default"
+
+[profile.prod]
+polling_interval_sec = 120
+
+[profile.other]
+[[profile.other.destination]]
+url = "ntfy://other"
+events = ["created"]
+"#;
+
+    #[test]
+    fn line_column_is_one_based() {
+        let contents = "a = 1\nbb = 2\n";
+        assert_eq!(line_column(contents, 0), (1, 1));
+        assert_eq!(line_column(contents, 4), (1, 5));
+        assert_eq!(line_column(contents, 6), (2, 1));
+        assert_eq!(line_column(contents, 9), (2, 4));
+        assert_eq!(line_column(contents, 100), (3, 1));
+    }
+
+    #[test]
+    fn profile_overrides_default_and_keeps_other_settings() {
+        let source = load_config("profile", DOCUMENT, Some("prod")).expect("valid config");
+        assert_eq!(source.values["POLLING_INTERVAL_SEC"].value, "120");
+        assert_eq!(source.values["DISCORD_REGIONS"].value, "en-US,de");
+        assert_eq!(source.values["NOTIFY_URLS"].value, "ntfy://a ntfy://b");
+        assert_eq!(source.destination_tables().len(), 1);
+        assert_eq!(source.destination_tables()[0].url, "ntfy://default");
+    }
+
+    #[test]
+    fn profile_destination_tables_replace_default_ones() {
+        let source = load_config("tables", DOCUMENT, Some("other")).expect("valid config");
+        let tables = source.destination_tables();
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].url, "ntfy://other");
+        assert_eq!(tables[0].events.as_deref(), Some(&["created".to_string()][..]));
+        assert!(tables[0].location.starts_with("destination #1 ("), "{}", tables[0].location);
+    }
+
+    #[test]
+    fn file_settings_are_read_from_the_named_file() {
+        let secret_path = env::temp_dir().join(format!("quest-secret-{}", std::process::id()));
+        std::fs::write(&secret_path, "from-file\n").expect("write secret");
+        let path = secret_path.display().to_string().replace('\\', "/");
+
+        let contents = format!("[default]\nmqtt_password = \"inline\"\n\n[profile.prod]\nmqtt_password_file = \"{path}\"\n");
+        let source = load_config("secret-file", &contents, Some("prod"));
+        let conflict = load_config("secret-conflict", &format!("[default]\nmqtt_password = \"a\"\nmqtt_password_file = \"{path}\"\n"), None);
+        let _ = std::fs::remove_file(&secret_path);
+
+        let source = source.expect("valid config");
+        assert_eq!(source.values["MQTT_PASSWORD"].value, "from-file");
+        assert!(!source.values.contains_key("MQTT_PASSWORD_FILE"));
+        assert!(matches!(conflict, Err(AppError::Config(m)) if m.contains("conflicts with 'mqtt_password'")));
+
+        let Err(AppError::Config(message)) = load_config("secret-missing", "[default]\nmqtt_password_file = \"/nonexistent/secret\"\n", None) else {
+            panic!("expected a configuration error");
+        };
+        assert!(message.starts_with("Failed to read MQTT_PASSWORD_FILE '/nonexistent/secret'"), "{message}");
+        assert!(message.ends_with(":2:22)"), "{message}");
+
+        assert!(load_config("not-a-file-setting", "[default]\npolling_interval_sec_file = \"x\"\n", None).is_err());
+    }
+
+    #[test]
+    fn account_tables_are_read_per_profile() {
+        let contents = r#"
+[default]
+[[default.account]]
+name = "main"
+token = "a"
+regions = ["de"]
+
+[[default.account]]
+name = "alt"
+token = "b"
+
+[profile.solo]
+[[profile.solo.account]]
+name = "solo"
+token = "c"
+"#;
+        let source = load_config("accounts", contents, None).expect("valid config");
+        let accounts = source.account_tables();
+        assert_eq!(accounts.iter().map(|a| a.name.as_str()).collect::<Vec<_>>(), ["main", "alt"]);
+        assert_eq!(accounts[0].regions.as_deref(), Some(&["de".to_string()][..]));
+        assert_eq!(accounts[1].regions, None);
+
+        let source = load_config("accounts-profile", contents, Some("solo")).expect("valid config");
+        assert_eq!(source.account_tables().len(), 1);
+        assert_eq!(source.account_tables()[0].token.as_deref(), Some("c"));
+
+        let both = "[default]\n[[default.account]]\nname = \"x\"\ntoken = \"a\"\ntoken_file = \"/tmp/t\"\n";
+        assert!(matches!(load_config("accounts-both", both, None), Err(AppError::Config(m)) if m.contains("both token and token_file")));
+    }
+
+    #[test]
+    fn file_errors_point_at_the_setting() {
+        let Err(AppError::Config(message)) = load_config("unknown", "[default]\npolling = 1\n", None) else {
+            panic!("expected a configuration error");
+        };
+        assert!(message.starts_with("Unknown setting 'polling' at "), "{message}");
+        assert!(message.ends_with(":2:1"), "{message}");
+
+        let Err(AppError::Config(message)) = load_config("missing-profile", DOCUMENT, Some("staging")) else {
+            panic!("expected a configuration error");
+        };
+        assert!(message.contains("Profile 'staging' not found"), "{message}");
+
+        let Err(AppError::Config(message)) = load_config("table-value", "[default]\nfeed_rss_path = { a = 1 }\n", None) else {
+            panic!("expected a configuration error");
+        };
+        assert!(message.contains("must be a string, number, boolean or array"), "{message}");
+    }
+}
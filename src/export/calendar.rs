@@ -0,0 +1,173 @@
+use crate::config::AppConfig;
+use crate::types::error::AppError;
+use crate::types::event::QuestComputed;
+use crate::types::state::TrackedQuest;
+use crate::communication::task::format_task_description;
+use crate::export::write_atomic;
+use chrono::{DateTime, Days, Utc};
+use std::path::Path;
+
+const PRODUCT_ID: &str = "-//Discord Quest Notification//Quest Calendar//EN";
+const UID_DOMAIN: &str = "discord-quest-notification";
+const MAX_LINE_OCTETS: usize = 75;
+
+/// Regenerate the iCalendar file configured in `CALENDAR_PATH`
+///
+/// # Errors
+///
+/// Returns `AppError` if the calendar file cannot be written
+pub fn write_calendar(config: &AppConfig, active: &[TrackedQuest], now: DateTime<Utc>) -> Result<(), AppError> {
+    let Some(path) = &config.calendar.path else {
+        return Ok(());
+    };
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{PRODUCT_ID}"),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        "X-WR-CALNAME:Discord Quests".to_string(),
+    ];
+
+    for tracked in active {
+        lines.extend(render_event(config, tracked, now));
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    let ics: String = lines.iter().map(|line| fold_line(line)).collect();
+    write_atomic(Path::new(path), &ics)
+}
+
+fn render_event(config: &AppConfig, tracked: &TrackedQuest, now: DateTime<Utc>) -> Vec<String> {
+    let quest = &tracked.quest;
+    let computed = QuestComputed::from_quest(quest, now);
+    let (Some(starts_at), Some(expires_at)) = (
+        computed.starts_at_unix.and_then(|ts| DateTime::from_timestamp(ts, 0)),
+        computed.expires_at_unix.and_then(|ts| DateTime::from_timestamp(ts, 0)),
+    ) else {
+        tracing::warn!("Skipping quest {} in calendar: invalid start or expiry date", quest.id);
+        return Vec::new();
+    };
+
+    let mut lines = vec![
+        "BEGIN:VEVENT".to_string(),
+        // The UID only depends on the quest ID so calendar clients update events in place
+        format!("UID:{}@{UID_DOMAIN}", quest.id),
+        format!("DTSTAMP:{}", format_utc(tracked.updated_at)),
+        format!("LAST-MODIFIED:{}", format_utc(tracked.updated_at)),
+    ];
+
+    if config.calendar.all_day {
+        let deadline = expires_at.date_naive();
+        let day_after = deadline.checked_add_days(Days::new(1)).unwrap_or(deadline);
+        lines.push(format!("DTSTART;VALUE=DATE:{}", deadline.format("%Y%m%d")));
+        lines.push(format!("DTEND;VALUE=DATE:{}", day_after.format("%Y%m%d")));
+        lines.push(format!("SUMMARY:{}", escape_text(&format!("Quest deadline: {}", quest.config.messages.game_title))));
+    } else {
+        lines.push(format!("DTSTART:{}", format_utc(starts_at)));
+        lines.push(format!("DTEND:{}", format_utc(expires_at)));
+        lines.push(format!("SUMMARY:{}", escape_text(&format!("Quest: {}", quest.config.messages.game_title))));
+    }
+
+    lines.push(format!("DESCRIPTION:{}", escape_text(&describe(tracked, &computed))));
+    lines.push(format!("URL:{}", computed.quest_url));
+    lines.push("TRANSP:TRANSPARENT".to_string());
+
+    for hours in &config.calendar.reminder_hours {
+        lines.extend([
+            "BEGIN:VALARM".to_string(),
+            "ACTION:DISPLAY".to_string(),
+            format!("DESCRIPTION:{}", escape_text(&format!("{} quest ends in {hours}h", quest.config.messages.game_title))),
+            alarm_trigger(config.calendar.all_day, expires_at, *hours),
+            "END:VALARM".to_string(),
+        ]);
+    }
+
+    lines.push("END:VEVENT".to_string());
+    lines
+}
+
+/// Alarms fire `hours` before the real expiry time. An all-day event ends at midnight
+/// after the deadline day rather than at `expires_at`, so its alarms use absolute times.
+fn alarm_trigger(all_day: bool, expires_at: DateTime<Utc>, hours: u32) -> String {
+    if all_day {
+        let fire_at = expires_at - chrono::Duration::hours(i64::from(hours));
+        format!("TRIGGER;VALUE=DATE-TIME:{}", format_utc(fire_at))
+    } else {
+        format!("TRIGGER;RELATED=END:-PT{hours}H")
+    }
+}
+
+fn describe(tracked: &TrackedQuest, computed: &QuestComputed) -> String {
+    let quest = &tracked.quest;
+    let reward = match (&computed.reward_name, &computed.reward_type) {
+        (Some(name), Some(reward_type)) => format!("{name} ({reward_type})"),
+        (Some(name), None) => name.clone(),
+        _ => "No reward details available".to_string(),
+    };
+
+    let mut description = format!(
+        "Reward: {reward}\nGame: {} ({})",
+        quest.config.messages.game_title, quest.config.messages.game_publisher
+    );
+    if let Some(orbs) = computed.orb_quantity {
+        description.push_str(&format!("\nOrbs: {orbs}"));
+    }
+    description.push_str("\nTasks:");
+    for task in quest.config.task_config.tasks.values() {
+        description.push('\n');
+        description.push_str(&format_task_description(&task.event_name, task.target));
+    }
+    description.push_str(&format!("\n{}", computed.quest_url));
+    description
+}
+
+fn format_utc(dt: DateTime<Utc>) -> String {
+    dt.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escape a TEXT property value (RFC 5545 section 3.3.11)
+fn escape_text(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Fold a content line at 75 octets without splitting UTF-8 characters, terminated by CRLF
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 8);
+    let mut line_octets = 0;
+    for c in line.chars() {
+        if line_octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            line_octets = 1;
+        }
+        folded.push(c);
+        line_octets += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_day_alarms_fire_relative_to_the_real_expiry() {
+        let expires_at = DateTime::parse_from_rfc3339("2026-03-10T18:30:00+00:00").expect("date").with_timezone(&Utc);
+        assert_eq!(alarm_trigger(true, expires_at, 24), "TRIGGER;VALUE=DATE-TIME:20260309T183000Z");
+        assert_eq!(alarm_trigger(false, expires_at, 2), "TRIGGER;RELATED=END:-PT2H");
+    }
+}
@@ -0,0 +1,183 @@
+use crate::config::AppConfig;
+use crate::types::constants::QUEST_URL_BASE;
+use crate::types::error::AppError;
+use crate::types::event::QuestComputed;
+use crate::types::state::TrackedQuest;
+use crate::export::write_atomic;
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::fmt::Write as FmtWrite;
+use std::path::Path;
+
+const FEED_TITLE: &str = "Discord Quests";
+const FEED_DESCRIPTION: &str = "Currently active Discord quests";
+
+/// Regenerate the Atom and RSS files configured in `FEED_ATOM_PATH` / `FEED_RSS_PATH`
+/// from the active quests, most recently seen first
+///
+/// # Errors
+///
+/// Returns `AppError` if a feed file cannot be written
+pub fn write_feeds(config: &AppConfig, active: &[TrackedQuest], now: DateTime<Utc>) -> Result<(), AppError> {
+    if config.feed_atom_path.is_none() && config.feed_rss_path.is_none() {
+        return Ok(());
+    }
+
+    let entries: Vec<FeedEntry> = active.iter()
+        .map(|tracked| FeedEntry::from_tracked(tracked, now))
+        .collect();
+
+    if let Some(path) = &config.feed_atom_path {
+        write_atomic(Path::new(path), &render_atom(&entries, now))?;
+    }
+    if let Some(path) = &config.feed_rss_path {
+        write_atomic(Path::new(path), &render_rss(&entries, now))?;
+    }
+
+    Ok(())
+}
+
+/// Everything a feed entry needs, independent of the output format
+struct FeedEntry {
+    id: String,
+    title: String,
+    link: String,
+    banner_url: String,
+    summary: String,
+    published: DateTime<Utc>,
+    updated: DateTime<Utc>,
+}
+
+impl FeedEntry {
+    fn from_tracked(tracked: &TrackedQuest, now: DateTime<Utc>) -> Self {
+        let quest = &tracked.quest;
+        let computed = QuestComputed::from_quest(quest, now);
+
+        let mut summary = String::new();
+        if let Some(name) = &computed.reward_name {
+            let _ = write!(summary, "Reward: {name}");
+            if let Some(reward_type) = &computed.reward_type {
+                let _ = write!(summary, " ({reward_type})");
+            }
+            if let Some(orbs) = computed.orb_quantity {
+                let _ = write!(summary, " - {orbs} Orbs");
+            }
+        } else {
+            summary.push_str("Reward: No reward details available");
+        }
+        let _ = write!(
+            summary,
+            "\nStarts: {}\nExpires: {}\nGame: {} ({})",
+            format_unix(computed.starts_at_unix),
+            format_unix(computed.expires_at_unix),
+            quest.config.messages.game_title,
+            quest.config.messages.game_publisher,
+        );
+
+        Self {
+            id: quest.id.clone(),
+            title: format!("New Quest - {}", quest.config.messages.game_title),
+            link: computed.quest_url,
+            banner_url: computed.banner_url,
+            summary,
+            published: tracked.first_seen,
+            updated: tracked.updated_at,
+        }
+    }
+}
+
+fn render_atom(entries: &[FeedEntry], now: DateTime<Utc>) -> String {
+    let updated = entries.iter().map(|e| e.updated).max().unwrap_or(now);
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    let _ = writeln!(xml, "  <id>{}</id>", escape_xml(QUEST_URL_BASE));
+    let _ = writeln!(xml, "  <title>{FEED_TITLE}</title>");
+    let _ = writeln!(xml, "  <subtitle>{FEED_DESCRIPTION}</subtitle>");
+    let _ = writeln!(xml, "  <link href=\"{}\"/>", escape_xml(QUEST_URL_BASE));
+    let _ = writeln!(xml, "  <updated>{}</updated>", updated.to_rfc3339_opts(SecondsFormat::Secs, true));
+
+    for entry in entries {
+        xml.push_str("  <entry>\n");
+        let _ = writeln!(xml, "    <id>{}</id>", escape_xml(&entry.link));
+        let _ = writeln!(xml, "    <title>{}</title>", escape_xml(&entry.title));
+        let _ = writeln!(xml, "    <link rel=\"alternate\" href=\"{}\"/>", escape_xml(&entry.link));
+        let _ = writeln!(
+            xml,
+            "    <link rel=\"enclosure\" type=\"{}\" href=\"{}\"/>",
+            image_mime_type(&entry.banner_url),
+            escape_xml(&entry.banner_url)
+        );
+        let _ = writeln!(xml, "    <published>{}</published>", entry.published.to_rfc3339_opts(SecondsFormat::Secs, true));
+        let _ = writeln!(xml, "    <updated>{}</updated>", entry.updated.to_rfc3339_opts(SecondsFormat::Secs, true));
+        let _ = writeln!(xml, "    <summary type=\"text\">{}</summary>", escape_xml(&entry.summary));
+        xml.push_str("  </entry>\n");
+    }
+
+    xml.push_str("</feed>\n");
+    xml
+}
+
+fn render_rss(entries: &[FeedEntry], now: DateTime<Utc>) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<rss version=\"2.0\">\n  <channel>\n");
+    let _ = writeln!(xml, "    <title>{FEED_TITLE}</title>");
+    let _ = writeln!(xml, "    <link>{}</link>", escape_xml(QUEST_URL_BASE));
+    let _ = writeln!(xml, "    <description>{FEED_DESCRIPTION}</description>");
+    let _ = writeln!(xml, "    <lastBuildDate>{}</lastBuildDate>", now.to_rfc2822());
+
+    for entry in entries {
+        xml.push_str("    <item>\n");
+        let _ = writeln!(xml, "      <title>{}</title>", escape_xml(&entry.title));
+        let _ = writeln!(xml, "      <link>{}</link>", escape_xml(&entry.link));
+        let _ = writeln!(xml, "      <guid isPermaLink=\"false\">{}</guid>", escape_xml(&entry.id));
+        let _ = writeln!(xml, "      <pubDate>{}</pubDate>", entry.published.to_rfc2822());
+        let _ = writeln!(xml, "      <description>{}</description>", escape_xml(&entry.summary));
+        // RSS requires a length; the banner size is unknown without downloading it
+        let _ = writeln!(
+            xml,
+            "      <enclosure url=\"{}\" length=\"0\" type=\"{}\"/>",
+            escape_xml(&entry.banner_url),
+            image_mime_type(&entry.banner_url)
+        );
+        xml.push_str("    </item>\n");
+    }
+
+    xml.push_str("  </channel>\n</rss>\n");
+    xml
+}
+
+fn format_unix(timestamp: Option<i64>) -> String {
+    timestamp
+        .and_then(|ts| DateTime::from_timestamp(ts, 0))
+        .map_or_else(|| "Unknown".to_string(), |dt| dt.format("%Y-%m-%d %H:%M UTC").to_string())
+}
+
+fn image_mime_type(url: &str) -> &'static str {
+    let path = url.split('?').next().unwrap_or(url).to_ascii_lowercase();
+    if path.ends_with(".jpg") || path.ends_with(".jpeg") {
+        "image/jpeg"
+    } else if path.ends_with(".gif") {
+        "image/gif"
+    } else if path.ends_with(".webp") {
+        "image/webp"
+    } else {
+        "image/png"
+    }
+}
+
+/// Escape text for use in XML element content and attribute values
+#[must_use]
+pub fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
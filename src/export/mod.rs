@@ -0,0 +1,58 @@
+pub mod calendar;
+pub mod feed;
+
+use crate::config::AppConfig;
+use crate::types::error::AppError;
+use crate::types::state::TrackedQuest;
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+
+/// Write `contents` to `path` via a temporary sibling file so readers never see a partial file
+///
+/// # Errors
+///
+/// Returns `AppError` if the temporary file cannot be written or renamed
+pub fn write_atomic(path: &Path, contents: &str) -> Result<(), AppError> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    std::fs::write(&tmp_path, contents)
+        .map_err(|e| AppError::Io(format!("Failed to write '{}': {e}", tmp_path.display())))?;
+    std::fs::rename(&tmp_path, path)
+        .map_err(|e| AppError::Io(format!("Failed to replace '{}': {e}", path.display())))?;
+
+    Ok(())
+}
+
+/// [`write_atomic`] on the blocking thread pool, for use from async code
+///
+/// # Errors
+///
+/// Returns `AppError` if the write fails or the blocking task panics
+pub async fn write_atomic_async(path: PathBuf, contents: String) -> Result<(), AppError> {
+    tokio::task::spawn_blocking(move || write_atomic(&path, &contents))
+        .await
+        .map_err(|e| AppError::Io(format!("File write task failed: {e}")))?
+}
+
+/// Regenerate the feed and calendar files from a snapshot of the active quests on the
+/// blocking thread pool. Failures are logged; a broken export never fails a poll.
+pub async fn write_exports(config: &AppConfig, active: Vec<TrackedQuest>, now: DateTime<Utc>) {
+    if config.feed_atom_path.is_none() && config.feed_rss_path.is_none() && config.calendar.path.is_none() {
+        return;
+    }
+
+    let config = config.clone();
+    let task = tokio::task::spawn_blocking(move || {
+        if let Err(e) = feed::write_feeds(&config, &active, now) {
+            tracing::error!("Failed to write quest feeds: {}", e);
+        }
+        if let Err(e) = calendar::write_calendar(&config, &active, now) {
+            tracing::error!("Failed to write quest calendar: {}", e);
+        }
+    });
+    if let Err(e) = task.await {
+        tracing::error!("Export task failed: {}", e);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::communication::text::QuestText;
+use crate::config::Account;
+use crate::types::secret::Secret;
+use std::collections::BTreeMap;
+
+/// Pauses polling for an account after Discord rejects its token, until a config reload
+/// replaces the token
+#[derive(Default)]
+pub struct TokenGuard {
+    rejected: BTreeMap<String, Secret>,
+}
+
+impl TokenGuard {
+    /// Whether polling `account` is paused because Discord rejected its token
+    #[must_use]
+    pub fn is_paused(&self, account: &Account) -> bool {
+        self.rejected.get(&account.name) == Some(&account.token)
+    }
+
+    /// Record that Discord rejected the token of `account`. Returns `true` the first time,
+    /// so the alert is only sent once per token.
+    pub fn reject(&mut self, account: &Account) -> bool {
+        let first = !self.is_paused(account);
+        self.rejected.insert(account.name.clone(), account.token.clone());
+        first
+    }
+
+    /// Names of the accounts whose polling is paused
+    #[must_use]
+    pub fn paused_accounts(&self) -> Vec<String> {
+        self.rejected.keys().cloned().collect()
+    }
+
+    /// Resume polling accounts whose rejected token was replaced or which were removed.
+    /// Returns the names of the accounts that were paused and are configured again.
+    pub fn resume_if_replaced(&mut self, accounts: &[Account]) -> Vec<String> {
+        let mut resumed = Vec::new();
+        self.rejected.retain(|name, token| match accounts.iter().find(|a| &a.name == name) {
+            Some(account) if &account.token != token => {
+                resumed.push(name.clone());
+                false
+            }
+            Some(_) => true,
+            None => false,
+        });
+        resumed
+    }
+}
+
+/// Alert sent to the ops destinations when Discord rejects an account's token
+#[must_use]
+pub fn token_rejected_alert(account: &str, reason: &str) -> QuestText {
+    QuestText {
+        title: format!("Discord token rejected for account '{account}'"),
+        body: format!(
+            "{reason}\nQuest polling for this account is paused. Replace its token and reload the config \
+             (edit the config file or send SIGHUP) to resume."
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::account;
+
+    fn with_token(name: &str, token: &str) -> Account {
+        Account { token: Secret::new(token), ..account(name) }
+    }
+
+    #[test]
+    fn guard_pauses_until_the_token_changes() {
+        let old = with_token("main", "old");
+        let new = with_token("main", "new");
+        let mut guard = TokenGuard::default();
+
+        assert!(!guard.is_paused(&old));
+        assert!(guard.resume_if_replaced(std::slice::from_ref(&old)).is_empty());
+        assert!(guard.reject(&old));
+        assert!(!guard.reject(&old), "the alert is only sent once");
+        assert!(guard.is_paused(&old));
+
+        assert!(guard.resume_if_replaced(std::slice::from_ref(&old)).is_empty(), "reloading the same token keeps polling paused");
+        assert_eq!(guard.resume_if_replaced(std::slice::from_ref(&new)), vec!["main".to_string()]);
+        assert!(!guard.is_paused(&new));
+        assert!(guard.reject(&new));
+    }
+
+    #[test]
+    fn guard_pauses_accounts_independently() {
+        let main = with_token("main", "a");
+        let alt = with_token("alt", "b");
+        let mut guard = TokenGuard::default();
+
+        assert!(guard.reject(&main));
+        assert!(guard.is_paused(&main));
+        assert!(!guard.is_paused(&alt));
+    }
+}
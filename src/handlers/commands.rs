@@ -0,0 +1,469 @@
+use crate::config::{Account, AppConfig};
+use crate::types::error::AppError;
+use crate::types::event::{QuestComputed, QuestEvent, QuestEventKind};
+use crate::types::quest::DiscordQuest;
+use crate::communication::{notifier::Notifier, scraper, text, webhook};
+use crate::handlers::lookup::{self, AccountPoll, PollOutcome};
+use crate::systemd;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as FmtWrite;
+use std::sync::{Arc, RwLock};
+use tokio::sync::broadcast;
+use tracing::{error, info, info_span, Instrument};
+
+/// One row of `list --json`
+#[derive(Serialize)]
+struct QuestSummary<'a> {
+    id: &'a str,
+    game_title: &'a str,
+    game_publisher: &'a str,
+    reward_name: Option<&'a str>,
+    reward_type: Option<&'a str>,
+    orb_quantity: Option<i32>,
+    starts_at: &'a str,
+    expires_at: &'a str,
+    is_expired: bool,
+    quest_url: &'a str,
+}
+
+/// Output of `show --json`
+#[derive(Serialize)]
+struct QuestDetails<'a> {
+    quest: &'a DiscordQuest,
+    computed: &'a QuestComputed,
+}
+
+/// Result of `once`, reported through the process exit code
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OnceStatus {
+    /// New quest events were found or queued deliveries were sent, and no delivery
+    /// failed. Events recorded silently on the first run, held for quiet hours or
+    /// rendered by a dry run count as found.
+    Delivered,
+    /// Every account and region was polled, no new quest event was found and nothing
+    /// was delivered
+    NoNewQuests,
+    /// Discord rejected the token of an account
+    Unauthorized,
+    /// An account or region could not be fetched
+    FetchFailed,
+    /// Some deliveries failed and were queued for the next run
+    DeliveryFailed,
+}
+
+impl OnceStatus {
+    /// Process exit code; 1 is left for configuration and state errors
+    #[must_use]
+    pub const fn exit_code(self) -> u8 {
+        match self {
+            Self::Delivered => 0,
+            Self::NoNewQuests => 3,
+            Self::Unauthorized => 4,
+            Self::FetchFailed => 5,
+            Self::DeliveryFailed => 6,
+        }
+    }
+
+    /// Pick the status of a run, preferring the most severe problem
+    #[must_use]
+    pub fn from_results(results: &[Result<PollOutcome, AppError>]) -> Self {
+        let errors = || results.iter().flat_map(|result| match result {
+            Ok(outcome) => outcome.fetch_errors.iter().map(|(_, e)| e).collect(),
+            Err(e) => vec![e],
+        });
+        if errors().any(|e| matches!(e, AppError::Unauthorized(_))) {
+            Self::Unauthorized
+        } else if errors().next().is_some() {
+            Self::FetchFailed
+        } else if results.iter().flatten().any(|o| o.failed > 0) {
+            Self::DeliveryFailed
+        } else if results.iter().flatten().all(|o| o.events == 0 && o.delivered == 0) {
+            Self::NoNewQuests
+        } else {
+            Self::Delivered
+        }
+    }
+}
+
+/// Poll every configured region of every account once, send notifications for quests not
+/// seen by a previous run and save the state
+///
+/// The first run only records the current quests (unless `PREVIOUS_QUEST=true`), so
+/// scheduled runs announce each quest exactly once. Deliveries that failed are kept in
+/// the state file and retried by the next run.
+///
+/// # Errors
+///
+/// Returns `AppError::Config` if `STATE_FILE` is not set outside a dry run, or an `AppError` if loading
+/// or saving state failed
+pub async fn once(config: &AppConfig) -> Result<OnceStatus, AppError> {
+    // A dry run only reads the state, so it also works without one
+    if config.state_file.is_none() && config.dry_run.is_none() {
+        return Err(AppError::Config(
+            "`once` needs STATE_FILE so quests already announced are remembered between runs".to_string()
+        ));
+    }
+
+    let saved_state = lookup::load_state(config)?;
+    let is_initial_run = saved_state.is_none();
+    let state = Arc::new(RwLock::new(saved_state.unwrap_or_default()));
+    let notifier = Notifier::new(config)?;
+    let (shutdown_tx, _) = broadcast::channel::<()>(1);
+    let mut results = Vec::new();
+
+    // Accounts are polled in parallel, one region each per round
+    let rounds = config.accounts.iter().map(|a| a.regions.len()).max().unwrap_or(0);
+    for round in 0..rounds {
+        let polls: Vec<AccountPoll> = config.accounts.iter()
+            .filter_map(|account| account.regions.get(round).map(|region| AccountPoll { account, region }))
+            .collect();
+        for poll in &polls {
+            info!("Checking quests for account {} in region: {}", poll.account.name, poll.region);
+        }
+        let result = lookup::app(config, &notifier, state.clone(), is_initial_run, &polls, shutdown_tx.subscribe())
+            .instrument(info_span!("poll", initial = is_initial_run, round = round + 1))
+            .await;
+        match &result {
+            Ok(outcome) => {
+                for (name, e) in &outcome.fetch_errors {
+                    error!("Error in app (account: {}, round: {}): {}", name, round + 1, e);
+                }
+            }
+            Err(e) => error!("Error in app: {}", e),
+        }
+        results.push(result);
+    }
+
+    // Saving after every fetch failed would turn the next run into a non-initial one
+    // with an empty state, announcing every active quest
+    let saved = if results.iter().flatten().any(|outcome| outcome.polled > 0) {
+        match lookup::clear_stale_state(&notifier, &state) {
+            Ok(()) => lookup::save_state(config, &state).await,
+            Err(e) => Err(e),
+        }
+    } else {
+        Ok(())
+    };
+    notifier.shutdown().await;
+    saved?;
+
+    Ok(OnceStatus::from_results(&results))
+}
+
+/// Print the quests an account can see in a region as a table or JSON
+///
+/// # Errors
+///
+/// Returns `AppError::NotFound` if the account is not configured, or another `AppError`
+/// if fetching quests fails
+pub async fn list(config: &AppConfig, account: Option<&str>, region: Option<&str>, json: bool) -> Result<(), AppError> {
+    let account = config.account(account)?;
+    let region = region.unwrap_or_else(|| default_region(account));
+    let quests = scraper::fetch_quests(account, region).await?;
+    let now = Utc::now();
+    let computed: Vec<QuestComputed> = quests.iter().map(|q| QuestComputed::from_quest(q, now)).collect();
+
+    if json {
+        let summaries: Vec<QuestSummary> = quests.iter().zip(&computed)
+            .map(|(quest, computed)| QuestSummary {
+                id: &quest.id,
+                game_title: &quest.config.messages.game_title,
+                game_publisher: &quest.config.messages.game_publisher,
+                reward_name: computed.reward_name.as_deref(),
+                reward_type: computed.reward_type.as_deref(),
+                orb_quantity: computed.orb_quantity,
+                starts_at: &quest.config.starts_at,
+                expires_at: &quest.config.expires_at,
+                is_expired: computed.is_expired,
+                quest_url: &computed.quest_url,
+            })
+            .collect();
+        println!("{}", to_pretty_json(&summaries)?);
+        return Ok(());
+    }
+
+    print!("{}", format_quest_table(&quests, &computed));
+    Ok(())
+}
+
+/// Print the details of one quest
+///
+/// # Errors
+///
+/// Returns `AppError::NotFound` if the account is not configured or the quest is not
+/// available in the region, or another `AppError` if fetching quests fails
+pub async fn show(
+    config: &AppConfig,
+    quest_id: &str,
+    account: Option<&str>,
+    region: Option<&str>,
+    json: bool,
+) -> Result<(), AppError> {
+    let account = config.account(account)?;
+    let region = region.unwrap_or_else(|| default_region(account));
+    let quest = find_quest(account, quest_id, region).await?;
+    let event = account_event(account, &quest, region);
+
+    if json {
+        let details = QuestDetails { quest: &event.quest, computed: &event.computed };
+        println!("{}", to_pretty_json(&details)?);
+        return Ok(());
+    }
+
+    let status = if event.computed.is_expired { "expired" } else { "active" };
+    println!("{} ({status})", quest.config.messages.game_title);
+    println!("ID: {}", quest.id);
+    println!("{}", text::format_quest_text(&event).body);
+    Ok(())
+}
+
+/// Print the Discord webhook body for a quest without sending it
+///
+/// # Errors
+///
+/// Returns `AppError::NotFound` if the account is not configured or the quest is not
+/// available in the region, or another `AppError` if fetching quests or building the payload fails
+pub async fn preview(config: &AppConfig, quest_id: &str, account: Option<&str>, region: Option<&str>) -> Result<(), AppError> {
+    let account = config.account(account)?;
+    let region = region.unwrap_or_else(|| default_region(account));
+    let quest = find_quest(account, quest_id, region).await?;
+    let payload = webhook::build_quest_payload(&Client::new(), config, &account_event(account, &quest, region)).await?;
+    println!("{}", to_pretty_json(&payload)?);
+    Ok(())
+}
+
+/// Send a sample quest to every configured destination and report each result
+///
+/// MQTT is left out so the sample is never retained on the broker.
+///
+/// # Errors
+///
+/// Returns `AppError::Request` if any destination failed
+pub async fn test_webhook(config: &AppConfig) -> Result<(), AppError> {
+    if config.destinations.is_empty() {
+        println!("No destinations configured (MQTT is not tested)");
+        return Ok(());
+    }
+
+    let now = Utc::now();
+    let event = QuestEvent::new(QuestEventKind::Created, &DiscordQuest::sample(now), config.primary_account().regions.clone(), now);
+    let results = Notifier::without_mqtt().send_to_all(config, &event).await;
+
+    let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+    for (label, result) in &results {
+        match result {
+            Ok(()) => println!("ok      {label}"),
+            Err(e) => println!("FAILED  {label}: {e}"),
+        }
+    }
+
+    if failed > 0 {
+        return Err(AppError::Request(format!("{failed} of {} destinations failed", results.len())));
+    }
+    Ok(())
+}
+
+/// Print a summary of a configuration that loaded successfully
+pub fn validate_config(config: &AppConfig) {
+    println!("Configuration is valid");
+    if let Some(path) = &config.location.path {
+        let profile = config.location.profile.as_deref().unwrap_or("default");
+        println!("  config file:      {} (profile: {profile})", path.display());
+    }
+    println!(
+        "  polling interval: {}s (jitter {}%, back-off up to {}s)",
+        config.polling_interval_sec, config.polling.jitter_percent, config.polling.backoff_max_sec
+    );
+    if let Some(schedule) = &config.polling.schedule {
+        for expression in &schedule.expressions {
+            println!("  poll schedule:    {expression} ({})", schedule.timezone);
+        }
+    }
+    if !config.polling.launch_windows.is_empty() {
+        println!(
+            "  launch windows:   {} (polling every {}s)",
+            config.polling.launch_windows.len(), config.polling.launch_interval_sec
+        );
+    }
+    for account in &config.accounts {
+        println!("  account:          {} (regions: {})", account.name, account.regions.join(", "));
+    }
+    println!("  previous quests:  {}", config.previous_quests);
+    println!("  destinations:     {}", config.destinations.len());
+    for route in &config.destinations {
+        let mut filters = Vec::new();
+        if let Some(events) = &route.events {
+            filters.push(format!("events: {}", events.iter().map(|e| e.as_str()).collect::<Vec<_>>().join(", ")));
+        }
+        if !route.regions.is_empty() {
+            filters.push(format!("regions: {}", route.regions.join(", ")));
+        }
+        if !route.progress.is_empty() {
+            filters.push(format!("progress: {}", route.progress.iter().map(|p| p.label()).collect::<Vec<_>>().join(", ")));
+        }
+        if let Some(quiet) = &route.quiet_hours {
+            let digest = if quiet.digest { ", digest" } else { "" };
+            filters.push(format!("quiet: {}-{} {}{digest}", quiet.start.format("%H:%M"), quiet.end.format("%H:%M"), quiet.timezone));
+        }
+        if filters.is_empty() {
+            println!("    - {}", route.label());
+        } else {
+            println!("    - {} ({})", route.label(), filters.join("; "));
+        }
+    }
+    for destination in &config.ops_destinations {
+        println!("  ops alerts:       {}", destination.label());
+    }
+    if let Some(digest) = &config.digest {
+        for expression in &digest.schedule.expressions {
+            println!("  digest schedule:  {expression} ({})", digest.schedule.timezone);
+        }
+        println!("  digest:           ending soon within {}h", digest.ending_hours);
+        for destination in &digest.destinations {
+            println!("    - {}", destination.label());
+        }
+    }
+    if let Some(mqtt) = &config.mqtt {
+        println!("  mqtt:             {}:{} ({})", mqtt.host, mqtt.port, mqtt.topic_prefix);
+    }
+    for path in [&config.feed_atom_path, &config.feed_rss_path, &config.calendar.path].into_iter().flatten() {
+        println!("  export:           {path}");
+    }
+}
+
+/// Print a systemd unit running this binary from the current directory, with the
+/// config file and profile in use
+///
+/// # Errors
+///
+/// Returns `AppError::Io` if the path of the executable or the current directory
+/// cannot be determined
+pub fn print_systemd_unit(config: &AppConfig) -> Result<(), AppError> {
+    let exe = std::env::current_exe().map_err(|e| AppError::Io(format!("Failed to locate the executable: {e}")))?;
+    let working_dir = std::env::current_dir().map_err(|e| AppError::Io(format!("Failed to read the current directory: {e}")))?;
+    print!("{}", systemd::unit_file(&exe, &working_dir, &config.location, config.shutdown_timeout_sec));
+    Ok(())
+}
+
+fn default_region(account: &Account) -> &str {
+    &account.regions[0]
+}
+
+async fn find_quest(account: &Account, quest_id: &str, region: &str) -> Result<DiscordQuest, AppError> {
+    scraper::fetch_quests(account, region).await?
+        .into_iter()
+        .find(|quest| quest.id == quest_id)
+        .ok_or_else(|| AppError::NotFound(format!("Quest {quest_id} is not available in region {region}")))
+}
+
+/// A `quest.created` event for a quest fetched by one account, with that account's progress
+fn account_event(account: &Account, quest: &DiscordQuest, region: &str) -> QuestEvent {
+    let mut event = QuestEvent::new(QuestEventKind::Created, quest, vec![region.to_string()], Utc::now());
+    let progress = quest.user_status.clone().unwrap_or_default().progress();
+    event.progress = BTreeMap::from([(account.name.clone(), progress)]);
+    event
+}
+
+fn to_pretty_json<T: Serialize>(value: &T) -> Result<String, AppError> {
+    serde_json::to_string_pretty(value).map_err(|e| AppError::Parse(e.to_string()))
+}
+
+fn format_quest_table(quests: &[DiscordQuest], computed: &[QuestComputed]) -> String {
+    const HEADERS: [&str; 5] = ["ID", "STATUS", "EXPIRES", "REWARD", "GAME"];
+
+    let rows: Vec<[String; 5]> = quests.iter().zip(computed)
+        .map(|(quest, computed)| [
+            quest.id.clone(),
+            if computed.is_expired { "expired" } else { "active" }.to_string(),
+            computed.expires_at_unix
+                .and_then(|ts| DateTime::from_timestamp(ts, 0))
+                .map_or_else(|| "unknown".to_string(), |dt| dt.format("%Y-%m-%d %H:%M").to_string()),
+            match (&computed.reward_name, computed.orb_quantity) {
+                (_, Some(orbs)) if orbs > 0 => format!("{orbs} Orbs"),
+                (Some(name), _) => name.clone(),
+                (None, _) => "-".to_string(),
+            },
+            quest.config.messages.game_title.clone(),
+        ])
+        .collect();
+
+    let mut widths = HEADERS.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut table = String::new();
+    for row in std::iter::once(HEADERS.map(String::from)).chain(rows) {
+        let line = row.iter().zip(widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        let _ = writeln!(table, "{}", line.trim_end());
+    }
+    if quests.is_empty() {
+        table.push_str("(no quests)\n");
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn polled(delivered: usize, failed: usize) -> Result<PollOutcome, AppError> {
+        Ok(PollOutcome { polled: 1, events: delivered + failed, delivered, failed, ..PollOutcome::default() })
+    }
+
+    #[test]
+    fn once_status_prefers_the_most_severe_problem() {
+        let unauthorized = Err(AppError::Unauthorized("401".to_string()));
+        let network = Err(AppError::Parse("bad body".to_string()));
+
+        assert_eq!(OnceStatus::from_results(&[polled(1, 1), network, unauthorized]), OnceStatus::Unauthorized);
+        assert_eq!(OnceStatus::from_results(&[polled(1, 1), Err(AppError::Parse(String::new()))]), OnceStatus::FetchFailed);
+        assert_eq!(OnceStatus::from_results(&[polled(1, 1), polled(2, 0)]), OnceStatus::DeliveryFailed);
+        assert_eq!(OnceStatus::from_results(&[polled(0, 0), polled(2, 0)]), OnceStatus::Delivered);
+        assert_eq!(OnceStatus::from_results(&[polled(0, 0), polled(0, 0)]), OnceStatus::NoNewQuests);
+
+        // One account failing while another is polled still counts
+        let rejected = PollOutcome {
+            fetch_errors: vec![("alt".to_string(), AppError::Unauthorized("401".to_string()))],
+            ..PollOutcome::default()
+        };
+        assert_eq!(OnceStatus::from_results(&[polled(2, 0), Ok(rejected)]), OnceStatus::Unauthorized);
+    }
+
+    #[test]
+    fn events_found_without_deliveries_are_not_a_quiet_run() {
+        // The first run records the active quests without notifying
+        let initial = PollOutcome { polled: 1, events: 5, ..PollOutcome::default() };
+        assert_eq!(OnceStatus::from_results(&[Ok(initial)]), OnceStatus::Delivered);
+
+        // Every destination is inside its quiet hours, so the events were held
+        let held = PollOutcome { polled: 1, events: 2, ..PollOutcome::default() };
+        assert_eq!(OnceStatus::from_results(&[Ok(held), polled(0, 0)]), OnceStatus::Delivered);
+
+        // Only a retried delivery from the outbox was sent
+        let retried = PollOutcome { polled: 1, delivered: 1, ..PollOutcome::default() };
+        assert_eq!(OnceStatus::from_results(&[Ok(retried)]), OnceStatus::Delivered);
+    }
+
+    #[test]
+    fn once_exit_codes_are_distinct() {
+        let codes = [
+            OnceStatus::Delivered,
+            OnceStatus::NoNewQuests,
+            OnceStatus::Unauthorized,
+            OnceStatus::FetchFailed,
+            OnceStatus::DeliveryFailed,
+        ].map(OnceStatus::exit_code);
+        let unique: std::collections::BTreeSet<u8> = codes.iter().copied().collect();
+        assert_eq!(unique.len(), codes.len());
+        assert!(!codes.contains(&1));
+    }
+}
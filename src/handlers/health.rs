@@ -0,0 +1,174 @@
+use crate::config::AppConfig;
+use crate::handlers::lookup::{AccountPoll, PollOutcome};
+use crate::types::state::QuestState;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::{Arc, RwLock};
+
+/// Runtime status shared with the HTTP listener
+pub type SharedStatus = Arc<RwLock<RuntimeStatus>>;
+
+/// What the monitor has done so far, served on `/status` and checked by `/readyz`
+#[derive(Clone, Debug, Serialize)]
+pub struct RuntimeStatus {
+    pub started_at: DateTime<Utc>,
+    /// Last poll in which at least one account was fetched
+    pub last_success: Option<DateTime<Utc>>,
+    pub regions: Vec<RegionStatus>,
+    pub known_quests: usize,
+    pub active_quests: usize,
+    pub pending_deliveries: usize,
+    /// Accounts whose token Discord rejected
+    pub paused_accounts: Vec<String>,
+    #[serde(skip)]
+    polling_interval_sec: u64,
+    #[serde(skip)]
+    ready_poll_intervals: u32,
+    #[serde(skip)]
+    ready_max_pending: usize,
+}
+
+/// Last poll of one account in one region
+#[derive(Clone, Debug, Serialize)]
+pub struct RegionStatus {
+    pub account: String,
+    pub region: String,
+    pub last_poll: DateTime<Utc>,
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+impl RuntimeStatus {
+    #[must_use]
+    pub fn new(config: &AppConfig, now: DateTime<Utc>) -> Self {
+        let mut status = Self {
+            started_at: now,
+            last_success: None,
+            regions: Vec::new(),
+            known_quests: 0,
+            active_quests: 0,
+            pending_deliveries: 0,
+            paused_accounts: Vec::new(),
+            polling_interval_sec: 0,
+            ready_poll_intervals: 0,
+            ready_max_pending: 0,
+        };
+        status.apply_config(config);
+        status
+    }
+
+    /// Take the polling interval and readiness thresholds from a (reloaded) config
+    pub fn apply_config(&mut self, config: &AppConfig) {
+        self.polling_interval_sec = config.polling.longest_wait_sec(config.polling_interval_sec);
+        self.ready_poll_intervals = config.readiness.poll_intervals;
+        self.ready_max_pending = config.readiness.max_pending;
+        self.regions.retain(|r| config.accounts.iter().any(|a| a.name == r.account));
+    }
+
+    /// Record the result of a poll and the resulting quest state
+    pub fn record_poll(&mut self, polls: &[AccountPoll<'_>], outcome: &PollOutcome, state: &QuestState, now: DateTime<Utc>) {
+        for poll in polls {
+            let error = outcome.fetch_errors.iter()
+                .find(|(name, _)| *name == poll.account.name)
+                .map(|(_, e)| e.to_string());
+            let index = self.regions.iter()
+                .position(|r| r.account == poll.account.name && r.region == poll.region)
+                .unwrap_or_else(|| {
+                    self.regions.push(RegionStatus {
+                        account: poll.account.name.clone(),
+                        region: poll.region.to_string(),
+                        last_poll: now,
+                        last_success: None,
+                        last_error: None,
+                    });
+                    self.regions.len() - 1
+                });
+            let region = &mut self.regions[index];
+            region.last_poll = now;
+            if error.is_none() {
+                region.last_success = Some(now);
+            }
+            region.last_error = error;
+        }
+        if outcome.polled > 0 {
+            self.last_success = Some(now);
+        }
+        self.known_quests = state.quests.len();
+        self.active_quests = state.active_quests().len();
+        self.pending_deliveries = state.outbox.len();
+    }
+
+    /// Reasons the monitor is not ready; empty when it is
+    #[must_use]
+    pub fn problems(&self, now: DateTime<Utc>) -> Vec<String> {
+        let mut problems = Vec::new();
+        let max_age = self.polling_interval_sec.saturating_mul(u64::from(self.ready_poll_intervals));
+        match self.last_success {
+            None => problems.push("no successful poll yet".to_string()),
+            Some(last) => {
+                let age = (now - last).num_seconds().max(0).unsigned_abs();
+                if age > max_age {
+                    problems.push(format!("last successful poll was {age}s ago (limit {max_age}s)"));
+                }
+            }
+        }
+        if !self.paused_accounts.is_empty() {
+            problems.push(format!("Discord rejected the token of: {}", self.paused_accounts.join(", ")));
+        }
+        if self.pending_deliveries > self.ready_max_pending {
+            problems.push(format!(
+                "{} deliveries pending (limit {})",
+                self.pending_deliveries, self.ready_max_pending
+            ));
+        }
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn status(now: DateTime<Utc>) -> RuntimeStatus {
+        RuntimeStatus {
+            started_at: now,
+            last_success: None,
+            regions: Vec::new(),
+            known_quests: 0,
+            active_quests: 0,
+            pending_deliveries: 0,
+            paused_accounts: Vec::new(),
+            polling_interval_sec: 300,
+            ready_poll_intervals: 3,
+            ready_max_pending: 10,
+        }
+    }
+
+    #[test]
+    fn ready_after_a_recent_successful_poll() {
+        let now = Utc::now();
+        let mut status = status(now);
+        assert_eq!(status.problems(now), vec!["no successful poll yet".to_string()]);
+
+        status.last_success = Some(now - Duration::seconds(600));
+        assert!(status.problems(now).is_empty());
+
+        status.last_success = Some(now - Duration::seconds(901));
+        assert_eq!(status.problems(now), vec!["last successful poll was 901s ago (limit 900s)".to_string()]);
+    }
+
+    #[test]
+    fn rejected_tokens_and_a_full_outbox_are_not_ready() {
+        let now = Utc::now();
+        let mut status = status(now);
+        status.last_success = Some(now);
+        status.paused_accounts = vec!["alt".to_string()];
+        status.pending_deliveries = 11;
+
+        let problems = status.problems(now);
+        assert_eq!(problems.len(), 2, "{problems:?}");
+        assert!(problems[0].contains("alt"));
+        assert_eq!(problems[1], "11 deliveries pending (limit 10)");
+    }
+}
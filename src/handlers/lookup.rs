@@ -1,73 +1,526 @@
-use crate::config::AppConfig;
+use crate::config::{Account, AppConfig, Destination, Route};
+use crate::types::digest::QuestDigest;
 use crate::types::error::AppError;
-use crate::communication::{scraper, webhook};
+use crate::types::event::{QuestEvent, QuestEventKind};
+use crate::types::state::{HeldDelivery, PendingDelivery, QuestState, TrackedQuest};
+use crate::communication::{notifier::Notifier, scraper};
 use crate::handlers::processor;
-use std::sync::{Arc, RwLock};
-use std::collections::HashSet;
-use std::hash::BuildHasher;
-use tracing::{info, error, warn};
-use reqwest::Client;
+use crate::metrics::metrics;
+use crate::export;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use chrono::{DateTime, Utc};
+use tracing::{info, info_span, error, warn, Instrument};
 use tokio::sync::broadcast;
 
+/// Deliveries are dropped from the outbox after this many failed attempts
+const MAX_DELIVERY_ATTEMPTS: u32 = 10;
+
+/// One account and the region it fetches in a poll
+#[derive(Clone, Copy, Debug)]
+pub struct AccountPoll<'a> {
+    pub account: &'a Account,
+    pub region: &'a str,
+}
+
+/// Fetches and deliveries made during one poll
+#[derive(Clone, Debug, Default)]
+pub struct PollOutcome {
+    /// Accounts whose quests were fetched
+    pub polled: usize,
+    /// Accounts whose fetch failed, with the error
+    pub fetch_errors: Vec<(String, AppError)>,
+    /// Quest events detected by this poll
+    pub events: usize,
+    /// Successful deliveries, including retried ones from the outbox
+    pub delivered: usize,
+    /// Failed deliveries, which were queued in the outbox
+    pub failed: usize,
+    /// Deliveries per destination label
+    pub deliveries: BTreeMap<String, DeliveryTally>,
+}
+
+/// Deliveries to one destination during a poll
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DeliveryTally {
+    pub sent: usize,
+    pub failed: usize,
+}
+
+impl PollOutcome {
+    /// Count a delivery attempt to `destination`
+    pub fn record_delivery(&mut self, destination: &str, sent: bool) {
+        let tally = self.deliveries.entry(destination.to_string()).or_default();
+        if sent {
+            tally.sent += 1;
+            self.delivered += 1;
+        } else {
+            tally.failed += 1;
+            self.failed += 1;
+        }
+    }
+}
+
 /// Main application loop for checking and processing quests
 ///
+/// Deliveries that failed on earlier polls are retried first. Every account in `polls` is
+/// then fetched in parallel and the results are merged, so a quest seen by several
+/// accounts is announced once. A failed fetch is recorded in the outcome and does not
+/// stop the other accounts. New delivery failures are queued in the state's outbox.
+/// Events a previous poll did not get to send, e.g. because of a shutdown, are sent
+/// before the new ones.
+///
 /// # Errors
 ///
-/// Returns `AppError` if:
-/// - Quest fetching from Discord API fails
-/// - State lock is poisoned
-/// - Webhook sending fails
-pub async fn app<S: BuildHasher + Clone + Send + Sync>(
+/// Returns `AppError` if the state lock is poisoned
+pub async fn app(
     config: &AppConfig,
-    state: Arc<RwLock<HashSet<String, S>>>,
+    notifier: &Notifier,
+    state: Arc<RwLock<QuestState>>,
     is_initial_run: bool,
-    region: &str,
+    polls: &[AccountPoll<'_>],
     mut shutdown_rx: broadcast::Receiver<()>,
-) -> Result<(), AppError> {
-    let client = Client::new();
-    let quests = scraper::fetch_quests(config, region).await?;
-    
-    let known_ids: HashSet<String, S> = {
-        let lock = state.read().map_err(|e| {
-            error!("Failed to acquire read lock on state: {}", e);
-            AppError::Config("State lock poisoned".to_string())
-        })?;
-        lock.clone()
+) -> Result<PollOutcome, AppError> {
+    let mut outcome = retry_outbox(config, notifier, &state).await?;
+    release_held(config, notifier, &state, &mut outcome).await?;
+
+    let fetches = polls.iter().map(|poll| {
+        scraper::fetch_quests(poll.account, poll.region)
+            .instrument(info_span!("fetch", account = %poll.account.name, region = poll.region))
+    });
+    let results = futures::future::join_all(fetches).await;
+    let now = Utc::now();
+
+    let mut fetched = Vec::new();
+    for (poll, result) in polls.iter().zip(results) {
+        metrics().polls.with_label_values(&[&poll.account.name, poll.region]).inc();
+        match result {
+            Ok(quests) => fetched.push((poll, quests)),
+            Err(e) => outcome.fetch_errors.push((poll.account.name.clone(), e)),
+        }
+    }
+    outcome.polled = fetched.len();
+    if fetched.is_empty() {
+        deliver_events(config, notifier, &state, &mut outcome, &mut shutdown_rx).await?;
+        return Ok(outcome);
+    }
+
+    let suppress = is_initial_run && !config.previous_quests;
+    let (events, active) = {
+        let mut lock = write_state(&state)?;
+        let mut events = Vec::new();
+        for (poll, quests) in fetched {
+            events.extend(processor::apply_fetched_quests(&mut lock, quests, poll.region, &poll.account.name, now));
+        }
+        events.extend(processor::expire_quests(&mut lock, now));
+        let events = processor::finalize_events(&lock, events, config.accounts.len() > 1);
+        // Queued together with marking the quests as seen, so an interrupted poll
+        // still sends them after a restart
+        if !suppress {
+            lock.undelivered.extend(events.iter().cloned());
+        }
+
+        let active: Vec<TrackedQuest> = lock.active_quests().into_iter().cloned().collect();
+        (events, active)
     };
+    outcome.events = events.len();
+    let discovered = events.iter().filter(|e| e.event == QuestEventKind::Created).count();
+    metrics().quests_discovered.inc_by(discovered as u64);
+
+    // Files are written after the lock is released, off the async runtime.
+    // Dry runs leave feeds and calendars untouched.
+    if config.dry_run.is_none() {
+        export::write_exports(config, active, now).await;
+    }
+
+    if suppress && !events.is_empty() {
+        info!("Initial fetch: Found {} quest events. Skipping notifications to prevent spam.", events.len());
+        // Retained MQTT topics still have to reflect the quests that are active right now
+        notifier.publish_state(&events);
+        return Ok(outcome);
+    }
+
+    if is_initial_run && !events.is_empty() {
+        info!("Initial fetch: Found {} quest events. Posting all previous quests (PREVIOUS_QUEST=true).", events.len());
+    }
+
+    deliver_events(config, notifier, &state, &mut outcome, &mut shutdown_rx).await?;
+    Ok(outcome)
+}
+
+/// Send the notifications of the undelivered events in the state, oldest first
+///
+/// Each event leaves the queue once every destination has received, failed or held it;
+/// failures go to the outbox and held notifications wait for the quiet hours to end.
+/// On a shutdown signal the remaining events stay queued for the next start.
+async fn deliver_events(
+    config: &AppConfig,
+    notifier: &Notifier,
+    state: &Arc<RwLock<QuestState>>,
+    outcome: &mut PollOutcome,
+    shutdown_rx: &mut broadcast::Receiver<()>,
+) -> Result<(), AppError> {
+    let total = read_state(state)?.undelivered.len();
+    for index in 0..total {
+        // Check for shutdown signal before processing each event
+        if shutdown_rx.try_recv().is_ok() {
+            warn!(
+                "Shutdown signal received. Stopping quest processing. Processed {}/{} events; the rest are sent after a restart.",
+                index, total
+            );
+            return Ok(());
+        }
+        let Some(event) = read_state(state)?.undelivered.first().cloned() else { break };
+
+        info!(
+            quest_id = %event.quest.id,
+            "Quest event {} ({}/{}): {}",
+            event.event.as_str(), index + 1, total, processor::format_quest_message(&event.quest)
+        );
+        let report = notifier.notify(config, &event).await;
+        for destination in &report.delivered {
+            outcome.record_delivery(destination, true);
+        }
+        for (destination, _) in &report.failed {
+            outcome.record_delivery(destination, false);
+        }
+
+        let mut lock = write_state(state)?;
+        lock.undelivered.remove(0);
+        for (destination, error) in report.failed {
+            enqueue(&mut lock, PendingDelivery {
+                destination,
+                event: event.clone(),
+                attempts: 1,
+                last_error: error.to_string(),
+                last_attempt: Utc::now(),
+            });
+        }
+        for destination in report.held {
+            info!("Holding {} for quest {} until the quiet hours of {} end", event.event.as_str(), event.quest.id, destination);
+            lock.held.push(HeldDelivery { destination, event: event.clone(), held_at: Utc::now() });
+        }
+    }
+    Ok(())
+}
+
+/// Retry every delivery in the outbox once, dropping entries whose destination was
+/// removed, whose quest expired in the meantime or that ran out of attempts
+async fn retry_outbox(
+    config: &AppConfig,
+    notifier: &Notifier,
+    state: &Arc<RwLock<QuestState>>,
+) -> Result<PollOutcome, AppError> {
+    let mut outcome = PollOutcome::default();
+    let pending = std::mem::take(&mut write_state(state)?.outbox);
+    if pending.is_empty() {
+        return Ok(outcome);
+    }
 
-    let new_quests = processor::filter_new_quests(quests, &known_ids);
+    info!("Retrying {} pending deliveries", pending.len());
+    let now = Utc::now();
+    let mut remaining = Vec::new();
 
-    if !new_quests.is_empty() {
-        {
-            let mut lock = state.write().map_err(|e| {
-                error!("Failed to acquire write lock on state: {}", e);
-                AppError::Config("State lock poisoned".to_string())
-            })?;
-            for quest in &new_quests {
-                lock.insert(quest.id.clone());
+    for mut delivery in pending {
+        let Some(route) = config.destinations.iter().find(|r| r.label() == delivery.destination) else {
+            info!("Dropping pending delivery for quest {}: {} is no longer configured", delivery.event.quest.id, delivery.destination);
+            continue;
+        };
+        let quest_ended = delivery.event.computed.expires_at_unix.is_some_and(|ts| ts <= now.timestamp());
+        if quest_ended && delivery.event.event != QuestEventKind::Expired {
+            info!("Dropping pending delivery for quest {} to {}: the quest has expired", delivery.event.quest.id, delivery.destination);
+            continue;
+        }
+
+        let result = notifier.deliver(config, route, &delivery.event).await;
+        outcome.record_delivery(&delivery.destination, result.is_ok());
+        if let Err(e) = result {
+            delivery.attempts += 1;
+            delivery.last_error = e.to_string();
+            delivery.last_attempt = Utc::now();
+            if delivery.attempts >= MAX_DELIVERY_ATTEMPTS {
+                error!(
+                    "Giving up on {} for quest {} to {} after {} attempts",
+                    delivery.event.event.as_str(), delivery.event.quest.id, delivery.destination, delivery.attempts
+                );
+            } else {
+                remaining.push(delivery);
             }
         }
+    }
 
-        if is_initial_run && !config.previous_quests {
-            info!("Initial fetch: Found {} quests. Skipping notifications to prevent spam.", new_quests.len());
-        } else {
-            if is_initial_run {
-                info!("Initial fetch: Found {} quests. Posting all previous quests (PREVIOUS_QUEST=true).", new_quests.len());
+    let mut lock = write_state(state)?;
+    for delivery in remaining {
+        enqueue(&mut lock, delivery);
+    }
+    Ok(outcome)
+}
+
+/// Deliver the notifications held for destinations whose quiet hours are over
+///
+/// A destination with `quiet_digest` receives several held notifications as one message;
+/// JSON webhooks always receive each event. Notifications whose quest expired in the
+/// meantime, or whose destination was removed, are dropped. Failed deliveries move to
+/// the outbox.
+async fn release_held(
+    config: &AppConfig,
+    notifier: &Notifier,
+    state: &Arc<RwLock<QuestState>>,
+    outcome: &mut PollOutcome,
+) -> Result<(), AppError> {
+    let held = std::mem::take(&mut write_state(state)?.held);
+    if held.is_empty() {
+        return Ok(());
+    }
+
+    let (still_held, due) = sort_held(&config.destinations, held, Utc::now());
+    let mut failed = Vec::new();
+    for (route, events) in due {
+        let label = route.label();
+        info!("Quiet hours of {} are over, delivering {} held notification(s)", label, events.len());
+        let digest = route.quiet_hours.as_ref().is_some_and(|quiet| quiet.digest)
+            && events.len() > 1
+            && !matches!(route.destination, Destination::Json(_));
+        if digest {
+            let result = notifier.deliver_quiet_digest(route, &events).await;
+            outcome.record_delivery(&label, result.is_ok());
+            if let Err(e) = result {
+                failed.extend(events.into_iter().map(|event| (label.clone(), event, e.to_string())));
             }
-            for (index, quest) in new_quests.iter().enumerate() {
-                // Check for shutdown signal before processing each quest
-                if shutdown_rx.try_recv().is_ok() {
-                    warn!("Shutdown signal received. Stopping quest processing. Processed {}/{} quests.", index, new_quests.len());
-                    return Ok(());
-                }
-
-                info!("Found new quest ({}/{}): {}", index + 1, new_quests.len(), processor::format_quest_message(quest));
-                if let Err(e) = webhook::send_webhook(&client, config, quest).await {
-                    error!("Failed to send webhook for quest {}: {}", quest.id, e);
-                }
+            continue;
+        }
+        for event in events {
+            let result = notifier.deliver(config, route, &event).await;
+            outcome.record_delivery(&label, result.is_ok());
+            if let Err(e) = result {
+                failed.push((label.clone(), event, e.to_string()));
             }
         }
     }
 
+    let mut lock = write_state(state)?;
+    lock.held.extend(still_held);
+    for (destination, event, last_error) in failed {
+        enqueue(&mut lock, PendingDelivery { destination, event, attempts: 1, last_error, last_attempt: Utc::now() });
+    }
     Ok(())
 }
+
+/// Split held notifications into those still inside their destination's quiet hours and
+/// those due now, grouped by destination; notifications that are no longer wanted are dropped
+fn sort_held(
+    routes: &[Route],
+    held: Vec<HeldDelivery>,
+    now: DateTime<Utc>,
+) -> (Vec<HeldDelivery>, Vec<(&Route, Vec<QuestEvent>)>) {
+    let mut still_held = Vec::new();
+    let mut due: Vec<(&Route, Vec<QuestEvent>)> = Vec::new();
+    for delivery in held {
+        let Some(route) = routes.iter().find(|r| r.label() == delivery.destination) else {
+            info!("Dropping held notification for quest {}: {} is no longer configured", delivery.event.quest.id, delivery.destination);
+            continue;
+        };
+        if route.quiet_hours.as_ref().is_some_and(|quiet| quiet.contains(now)) {
+            still_held.push(delivery);
+            continue;
+        }
+        let quest_ended = delivery.event.computed.expires_at_unix.is_some_and(|ts| ts <= now.timestamp());
+        if quest_ended && delivery.event.event != QuestEventKind::Expired {
+            info!("Dropping held notification for quest {} to {}: the quest has expired", delivery.event.quest.id, delivery.destination);
+            continue;
+        }
+        match due.iter_mut().find(|(r, _)| r.label() == delivery.destination) {
+            Some((_, events)) => events.push(delivery.event),
+            None => due.push((route, vec![delivery.event])),
+        }
+    }
+    (still_held, due)
+}
+
+/// Queue a failed delivery, replacing an older entry for the same quest event and destination
+fn enqueue(state: &mut QuestState, delivery: PendingDelivery) {
+    state.outbox.retain(|pending| {
+        pending.destination != delivery.destination
+            || pending.event.quest.id != delivery.event.quest.id
+            || pending.event.event != delivery.event.event
+    });
+    state.outbox.push(delivery);
+}
+
+fn read_state(state: &RwLock<QuestState>) -> Result<RwLockReadGuard<'_, QuestState>, AppError> {
+    state.read().map_err(|_| AppError::Config("State lock poisoned".to_string()))
+}
+
+fn write_state(state: &RwLock<QuestState>) -> Result<RwLockWriteGuard<'_, QuestState>, AppError> {
+    state.write().map_err(|e| {
+        error!("Failed to acquire write lock on state: {}", e);
+        AppError::Config("State lock poisoned".to_string())
+    })
+}
+
+/// Which region each account fetches next
+///
+/// Each poll fetches one region per account, cycling through the account's region list.
+#[derive(Debug, Default)]
+pub struct RegionRotation {
+    next: BTreeMap<String, usize>,
+    cycled: BTreeSet<String>,
+}
+
+impl RegionRotation {
+    /// The region `account` fetches in the next poll
+    #[must_use]
+    pub fn current<'a>(&self, account: &'a Account) -> &'a str {
+        let index = self.next.get(&account.name).copied().unwrap_or(0);
+        account.regions.get(index).or(account.regions.first()).map_or("en-US", String::as_str)
+    }
+
+    /// Move `account` to its next region after a poll
+    pub fn advance(&mut self, account: &Account) {
+        let next = self.next.entry(account.name.clone()).or_insert(0);
+        *next = (*next + 1) % account.regions.len().max(1);
+        if *next == 0 {
+            self.cycled.insert(account.name.clone());
+        }
+    }
+
+    /// Whether every configured account has polled all of its regions at least once
+    #[must_use]
+    pub fn all_cycled(&self, config: &AppConfig) -> bool {
+        config.accounts.iter().all(|account| self.cycled.contains(&account.name))
+    }
+
+    /// Drop positions of removed accounts and restart accounts whose region list shrank
+    pub fn retain(&mut self, config: &AppConfig) {
+        self.next.retain(|name, index| {
+            config.accounts.iter().find(|a| &a.name == name).is_some_and(|a| *index < a.regions.len())
+        });
+        self.cycled.retain(|name| config.accounts.iter().any(|a| &a.name == name));
+    }
+}
+
+/// Clear retained MQTT topics of quests that are no longer active. Only call this once
+/// every account has polled every region, so quests from regions not polled yet are not cleared.
+///
+/// # Errors
+///
+/// Returns `AppError` if the state lock is poisoned
+pub fn clear_stale_state(notifier: &Notifier, state: &Arc<RwLock<QuestState>>) -> Result<(), AppError> {
+    let lock = read_state(state)?;
+    notifier.clear_stale_state(&lock);
+    Ok(())
+}
+
+/// Send the scheduled digest of the active quests to the `DIGEST_URLS` destinations and
+/// remember when it was sent, so the next digest lists the quests seen since
+///
+/// # Errors
+///
+/// Returns `AppError` if the state lock is poisoned or the state cannot be saved
+pub async fn send_digest(
+    config: &AppConfig,
+    notifier: &Notifier,
+    state: &Arc<RwLock<QuestState>>,
+    now: DateTime<Utc>,
+) -> Result<(), AppError> {
+    let Some(digest_config) = &config.digest else { return Ok(()) };
+    let digest = {
+        let lock = read_state(state)?;
+        let period_start = digest_config.period_start(lock.last_digest_at, now);
+        QuestDigest::build(&lock, period_start, digest_config.ending_hours, now)
+    };
+    notifier.send_digest(&digest_config.destinations, &digest).await;
+    write_state(state)?.last_digest_at = Some(now);
+    save_state(config, state).await
+}
+
+/// Load the quest state saved by a previous run, if `STATE_FILE` is set and the file exists
+///
+/// # Errors
+///
+/// Returns `AppError` if the state file exists but cannot be read or parsed
+pub fn load_state(config: &AppConfig) -> Result<Option<QuestState>, AppError> {
+    let Some(path) = &config.state_file else {
+        return Ok(None);
+    };
+    let state = QuestState::load(Path::new(path))?;
+    if let Some(state) = &state {
+        info!("Loaded {} known quests from {}", state.quests.len(), path);
+    }
+    Ok(state)
+}
+
+/// Save the quest state to `STATE_FILE`, if configured. Dry runs never write it.
+///
+/// # Errors
+///
+/// Returns `AppError` if the state lock is poisoned or the file cannot be written
+pub async fn save_state(config: &AppConfig, state: &Arc<RwLock<QuestState>>) -> Result<(), AppError> {
+    let Some(path) = config.state_file.as_ref().filter(|_| config.dry_run.is_none()) else {
+        return Ok(());
+    };
+    let contents = read_state(state)?.to_json()?;
+    export::write_atomic_async(PathBuf::from(path), contents).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{account, quest};
+
+    #[test]
+    fn rotation_cycles_each_account_through_its_regions() {
+        let main = Account { regions: vec!["en-US".to_string(), "de".to_string()], ..account("main") };
+        let alt = Account { regions: vec!["fr".to_string()], ..account("alt") };
+        let mut rotation = RegionRotation::default();
+
+        assert_eq!(rotation.current(&main), "en-US");
+        rotation.advance(&main);
+        rotation.advance(&alt);
+        assert_eq!(rotation.current(&main), "de");
+        assert_eq!(rotation.current(&alt), "fr");
+        assert!(rotation.cycled.contains("alt") && !rotation.cycled.contains("main"));
+
+        rotation.advance(&main);
+        assert_eq!(rotation.current(&main), "en-US");
+        assert!(rotation.cycled.contains("main"));
+    }
+
+    fn held(destination: &str, kind: QuestEventKind, expires_at: &str) -> HeldDelivery {
+        let mut quest = quest("1");
+        quest.config.expires_at = expires_at.to_string();
+        let event = QuestEvent::new(kind, &quest, vec!["en-US".to_string()], Utc::now());
+        HeldDelivery { destination: destination.to_string(), event, held_at: Utc::now() }
+    }
+
+    #[test]
+    fn held_notifications_wait_for_the_end_of_their_quiet_hours() {
+        use crate::config::destination::{parse_destination_url, parse_quiet_hours};
+        use chrono::TimeZone;
+
+        let mut night = Route::new(parse_destination_url("ntfy://night", "NOTIFY_URLS").expect("valid destination"));
+        night.quiet_hours = Some(parse_quiet_hours("23:00-07:00", None, true).expect("valid quiet hours"));
+        let day = Route::new(parse_destination_url("ntfy://day", "NOTIFY_URLS").expect("valid destination"));
+        let routes = [night, day];
+        let future = "2099-01-01T00:00:00+00:00";
+        let held = || vec![
+            held("ntfy:ntfy.sh/night", QuestEventKind::Created, future),
+            held("ntfy:ntfy.sh/day", QuestEventKind::Created, future),
+            held("ntfy:ntfy.sh/day", QuestEventKind::Created, "2026-01-02T00:00:00+00:00"),
+            held("ntfy:ntfy.sh/day", QuestEventKind::Expired, "2026-01-02T00:00:00+00:00"),
+            held("ntfy:ntfy.sh/removed", QuestEventKind::Created, future),
+        ];
+
+        let (still_held, due) = sort_held(&routes, held(), Utc.with_ymd_and_hms(2026, 7, 1, 23, 30, 0).unwrap());
+        assert_eq!(still_held.len(), 1);
+        assert_eq!(still_held[0].destination, "ntfy:ntfy.sh/night");
+        assert_eq!(due.len(), 1);
+        let kinds: Vec<_> = due[0].1.iter().map(|e| e.event).collect();
+        assert_eq!(kinds, [QuestEventKind::Created, QuestEventKind::Expired], "ended quests only keep their expiry");
+
+        let (still_held, due) = sort_held(&routes, held(), Utc.with_ymd_and_hms(2026, 7, 1, 8, 0, 0).unwrap());
+        assert!(still_held.is_empty());
+        assert_eq!(due.len(), 2);
+    }
+}
@@ -1,2 +1,8 @@
 pub mod processor;
 pub mod lookup;
+pub mod commands;
+pub mod preflight;
+pub mod auth;
+pub mod health;
+pub mod monitor;
+pub mod schedule;
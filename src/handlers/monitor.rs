@@ -0,0 +1,165 @@
+use crate::communication::text::QuestText;
+use crate::config::{AlertConfig, AppConfig, Route};
+use crate::handlers::lookup::{AccountPoll, PollOutcome};
+use crate::types::error::AppError;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Watches the monitor's own fetches and deliveries and raises an ops alert when one
+/// keeps failing, and a recovery message once it works again
+///
+/// Rejected tokens are left to [`crate::handlers::auth::TokenGuard`], which pauses the
+/// account and sends its own alert.
+#[derive(Debug, Default)]
+pub struct OpsMonitor {
+    /// Consecutive polls in which each account's fetch failed
+    fetch_failures: BTreeMap<String, u32>,
+    /// Consecutive failed deliveries to each destination
+    delivery_failures: BTreeMap<String, u32>,
+    /// Accounts and destinations with an alert that has not recovered yet
+    alerted_accounts: BTreeSet<String>,
+    alerted_destinations: BTreeSet<String>,
+}
+
+impl OpsMonitor {
+    /// Update the failure counts with a poll's outcome and return the alerts and
+    /// recovery messages to send
+    pub fn record(&mut self, alerts: &AlertConfig, polls: &[AccountPoll], outcome: &PollOutcome) -> Vec<QuestText> {
+        let mut messages = Vec::new();
+
+        for poll in polls {
+            let name = &poll.account.name;
+            let error = outcome.fetch_errors.iter().find(|(account, _)| account == name).map(|(_, e)| e);
+            match error {
+                Some(AppError::Unauthorized(_)) => {}
+                Some(error) => {
+                    let count = self.fetch_failures.entry(name.clone()).or_insert(0);
+                    *count += 1;
+                    if alerts.fetch_failures > 0 && *count >= alerts.fetch_failures && self.alerted_accounts.insert(name.clone()) {
+                        messages.push(QuestText {
+                            title: format!("Quest fetches failing for account '{name}'"),
+                            body: format!("{count} consecutive polls of account {name} failed. Last error: {error}"),
+                        });
+                    }
+                }
+                None => {
+                    self.fetch_failures.remove(name);
+                    if self.alerted_accounts.remove(name) {
+                        messages.push(QuestText {
+                            title: format!("Quest fetches recovered for account '{name}'"),
+                            body: format!("Account {name} fetched quests successfully again (region {}).", poll.region),
+                        });
+                    }
+                }
+            }
+        }
+
+        for (destination, tally) in &outcome.deliveries {
+            if tally.sent > 0 {
+                self.delivery_failures.remove(destination);
+                if self.alerted_destinations.remove(destination) {
+                    messages.push(QuestText {
+                        title: format!("Deliveries recovered for {destination}"),
+                        body: format!("{destination} accepted a notification again; queued deliveries are being retried."),
+                    });
+                }
+                continue;
+            }
+            let count = self.delivery_failures.entry(destination.clone()).or_insert(0);
+            *count += u32::try_from(tally.failed).unwrap_or(u32::MAX);
+            if alerts.delivery_failures > 0 && *count >= alerts.delivery_failures && self.alerted_destinations.insert(destination.clone()) {
+                messages.push(QuestText {
+                    title: format!("Deliveries failing for {destination}"),
+                    body: format!(
+                        "{count} consecutive deliveries to {destination} failed. They stay queued and are retried every poll."
+                    ),
+                });
+            }
+        }
+
+        messages
+    }
+
+    /// Forget accounts and destinations that are no longer configured
+    pub fn retain(&mut self, config: &AppConfig) {
+        let accounts: BTreeSet<&str> = config.accounts.iter().map(|a| a.name.as_str()).collect();
+        let destinations: BTreeSet<String> = config.destinations.iter().map(Route::label).collect();
+        self.fetch_failures.retain(|name, _| accounts.contains(name.as_str()));
+        self.alerted_accounts.retain(|name| accounts.contains(name.as_str()));
+        self.delivery_failures.retain(|label, _| destinations.contains(label));
+        self.alerted_destinations.retain(|label| destinations.contains(label));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::account;
+
+    const ALERTS: AlertConfig = AlertConfig { fetch_failures: 2, delivery_failures: 3 };
+
+    fn failed_fetch(name: &str, error: AppError) -> PollOutcome {
+        PollOutcome { fetch_errors: vec![(name.to_string(), error)], ..PollOutcome::default() }
+    }
+
+    #[test]
+    fn fetch_alert_after_threshold_and_recovery_once() {
+        let main = account("main");
+        let polls = [AccountPoll { account: &main, region: "en-US" }];
+        let mut monitor = OpsMonitor::default();
+        let error = || failed_fetch("main", AppError::Request("timeout".to_string()));
+
+        assert!(monitor.record(&ALERTS, &polls, &error()).is_empty());
+        let alerts = monitor.record(&ALERTS, &polls, &error());
+        assert_eq!(alerts.len(), 1);
+        assert!(alerts[0].title.contains("failing"));
+        assert!(monitor.record(&ALERTS, &polls, &error()).is_empty(), "alerted only once");
+
+        let recovered = monitor.record(&ALERTS, &polls, &PollOutcome { polled: 1, ..PollOutcome::default() });
+        assert_eq!(recovered.len(), 1);
+        assert!(recovered[0].title.contains("recovered"));
+        assert!(monitor.record(&ALERTS, &polls, &PollOutcome::default()).is_empty());
+    }
+
+    #[test]
+    fn rejected_tokens_are_left_to_the_token_guard() {
+        let main = account("main");
+        let polls = [AccountPoll { account: &main, region: "en-US" }];
+        let mut monitor = OpsMonitor::default();
+        for _ in 0..3 {
+            let outcome = failed_fetch("main", AppError::Unauthorized("401".to_string()));
+            assert!(monitor.record(&ALERTS, &polls, &outcome).is_empty());
+        }
+    }
+
+    #[test]
+    fn delivery_failures_accumulate_until_a_send_succeeds() {
+        let mut monitor = OpsMonitor::default();
+        let mut outcome = PollOutcome::default();
+        outcome.record_delivery("discord:1", false);
+        outcome.record_delivery("discord:1", false);
+        outcome.record_delivery("ntfy:quests", true);
+        assert!(monitor.record(&ALERTS, &[], &outcome).is_empty());
+
+        let mut outcome = PollOutcome::default();
+        outcome.record_delivery("discord:1", false);
+        let alerts = monitor.record(&ALERTS, &[], &outcome);
+        assert_eq!(alerts.len(), 1);
+        assert!(alerts[0].title.contains("discord:1"));
+
+        let mut outcome = PollOutcome::default();
+        outcome.record_delivery("discord:1", true);
+        assert_eq!(monitor.record(&ALERTS, &[], &outcome).len(), 1);
+        assert!(monitor.delivery_failures.is_empty());
+    }
+
+    #[test]
+    fn zero_threshold_disables_alerts() {
+        let alerts = AlertConfig { fetch_failures: 0, delivery_failures: 0 };
+        let mut monitor = OpsMonitor::default();
+        let mut outcome = PollOutcome::default();
+        for _ in 0..10 {
+            outcome.record_delivery("discord:1", false);
+        }
+        assert!(monitor.record(&alerts, &[], &outcome).is_empty());
+    }
+}
@@ -0,0 +1,79 @@
+use crate::config::{AppConfig, Destination, PreflightMode};
+use crate::types::error::AppError;
+use crate::communication::{scraper, webhook};
+use reqwest::Client;
+use tracing::{info, warn};
+
+/// Check the token of every account and every Discord webhook before polling starts
+///
+/// Definite failures (a rejected token, a deleted webhook) stop startup in strict mode
+/// and are logged as warnings in warn mode. Checks that cannot complete, e.g. because
+/// Discord is unreachable, only log a warning.
+///
+/// # Errors
+///
+/// Returns `AppError::Unauthorized` if a token was rejected, or `AppError::Config`
+/// listing every webhook that does not exist, in strict mode
+pub async fn check(config: &AppConfig) -> Result<(), AppError> {
+    if config.preflight == PreflightMode::Off {
+        return Ok(());
+    }
+
+    let mut token_errors = Vec::new();
+    let mut failures = Vec::new();
+
+    for account in &config.accounts {
+        match scraper::fetch_current_user(account).await {
+            Ok(username) => info!("Discord token of account {} is valid (user: {})", account.name, username),
+            Err(AppError::Unauthorized(message)) => token_errors.push(format!("account {}: {message}", account.name)),
+            Err(e) => warn!("Could not verify the token of account {}: {}", account.name, e),
+        }
+    }
+    // Channel and guild names are looked up with the first account
+    let account = config.primary_account();
+
+    let client = Client::new();
+    for route in &config.destinations {
+        let Destination::Discord(discord) = &route.destination else { continue };
+        let label = route.label();
+
+        match webhook::fetch_webhook_info(&client, &discord.webhook_url).await {
+            Ok(info) => {
+                // Channel and guild names need a shared guild
+                let channel = match &info.channel_id {
+                    Some(id) => scraper::fetch_name(account, &format!("channels/{id}")).await
+                        .map_or_else(|| id.clone(), |name| format!("#{name}")),
+                    None => "unknown channel".to_string(),
+                };
+                let guild = match &info.guild_id {
+                    Some(id) => scraper::fetch_name(account, &format!("guilds/{id}")).await.unwrap_or_else(|| id.clone()),
+                    None => "unknown guild".to_string(),
+                };
+                info!(
+                    "Webhook {} ({}) posts to {} in {}",
+                    label, info.name.as_deref().unwrap_or("unnamed"), channel, guild
+                );
+            }
+            Err(AppError::NotFound(message)) => failures.push(format!("{label}: {message}")),
+            Err(e) => warn!("Could not verify webhook {}: {}", label, e),
+        }
+    }
+
+    if config.preflight == PreflightMode::Warn {
+        for problem in token_errors.iter().chain(&failures) {
+            warn!("Preflight check failed, continuing in degraded mode: {}", problem);
+        }
+        return Ok(());
+    }
+
+    if !token_errors.is_empty() {
+        return Err(AppError::Unauthorized(token_errors.join("; ")));
+    }
+    if !failures.is_empty() {
+        return Err(AppError::Config(format!(
+            "Preflight check failed (set PREFLIGHT=warn to start anyway): {}",
+            failures.join("; ")
+        )));
+    }
+    Ok(())
+}
@@ -1,12 +1,113 @@
-use crate::types::quest::DiscordQuest;
-use std::collections::HashSet;
-use std::hash::BuildHasher;
+use crate::types::event::{QuestEvent, QuestEventKind};
+use crate::types::quest::{DiscordQuest, QuestConfig};
+use crate::types::state::{QuestState, TrackedQuest};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, BTreeSet};
 
-#[must_use]
-pub fn filter_new_quests<S: BuildHasher>(fetched_quests: Vec<DiscordQuest>, known_ids: &HashSet<String, S>) -> Vec<DiscordQuest> {
-    fetched_quests.into_iter()
-        .filter(|q| !known_ids.contains(&q.id))
-        .collect()
+/// Merge quests fetched by `account` for `region` into `state` and return the resulting events
+///
+/// New quests produce `quest.created`. Known quests produce `quest.updated` when a
+/// locale-independent field changed; localized text differs per region and is ignored.
+pub fn apply_fetched_quests(
+    state: &mut QuestState,
+    fetched_quests: Vec<DiscordQuest>,
+    region: &str,
+    account: &str,
+    now: DateTime<Utc>,
+) -> Vec<QuestEvent> {
+    let mut events = Vec::new();
+
+    for mut quest in fetched_quests {
+        // User status belongs to the fetching account, not to the quest
+        let user_status = quest.user_status.take().unwrap_or_default();
+
+        if let Some(tracked) = state.quests.get_mut(&quest.id) {
+            tracked.regions.insert(region.to_string());
+            tracked.accounts.insert(account.to_string());
+            tracked.user_status.insert(account.to_string(), user_status);
+            if has_material_change(&tracked.quest.config, &quest.config) {
+                tracked.quest = quest;
+                tracked.updated_at = now;
+                tracked.expired = false;
+                events.push(QuestEvent::new(QuestEventKind::Updated, &tracked.quest, tracked.regions_vec(), now));
+            }
+            continue;
+        }
+
+        let tracked = TrackedQuest {
+            quest,
+            regions: BTreeSet::from([region.to_string()]),
+            accounts: BTreeSet::from([account.to_string()]),
+            user_status: BTreeMap::from([(account.to_string(), user_status)]),
+            first_seen: now,
+            updated_at: now,
+            expired: false,
+        };
+        events.push(QuestEvent::new(QuestEventKind::Created, &tracked.quest, tracked.regions_vec(), now));
+        state.quests.insert(tracked.quest.id.clone(), tracked);
+    }
+
+    events
+}
+
+/// Mark quests whose `expires_at` has passed and return a `quest.expired` event for each
+pub fn expire_quests(state: &mut QuestState, now: DateTime<Utc>) -> Vec<QuestEvent> {
+    let mut events = Vec::new();
+
+    for tracked in state.quests.values_mut().filter(|t| !t.expired) {
+        let expired = DateTime::parse_from_rfc3339(&tracked.quest.config.expires_at)
+            .is_ok_and(|dt| dt.with_timezone(&Utc) <= now);
+        if expired {
+            tracked.expired = true;
+            events.push(QuestEvent::new(QuestEventKind::Expired, &tracked.quest, tracked.regions_vec(), now));
+        }
+    }
+
+    events
+}
+
+/// Compare the parts of a quest that are the same in every locale
+fn has_material_change(old: &QuestConfig, new: &QuestConfig) -> bool {
+    let reward_key = |config: &QuestConfig| {
+        config.rewards_config.rewards.iter()
+            .map(|r| (r.reward_type, r.sku_id.clone(), r.orb_quantity, r.quantity, r.asset.clone()))
+            .collect::<Vec<_>>()
+    };
+
+    old.starts_at != new.starts_at
+        || old.expires_at != new.expires_at
+        || old.features != new.features
+        || old.assets != new.assets
+        || old.application.id != new.application.id
+        || old.task_config != new.task_config
+        || old.rewards_config.platforms != new.rewards_config.platforms
+        || reward_key(old) != reward_key(new)
+}
+
+/// Merge events for the same quest and kind produced by several fetches of one poll,
+/// and refresh their regions, accounts and progress from `state`
+///
+/// Accounts are only listed when `list_accounts` is set, i.e. several accounts are configured.
+pub fn finalize_events(state: &QuestState, events: Vec<QuestEvent>, list_accounts: bool) -> Vec<QuestEvent> {
+    let mut merged: Vec<QuestEvent> = Vec::with_capacity(events.len());
+    for event in events {
+        match merged.iter_mut().find(|e| e.quest.id == event.quest.id && e.event == event.event) {
+            // The later fetch carries the newer quest data
+            Some(existing) => *existing = event,
+            None => merged.push(event),
+        }
+    }
+
+    for event in &mut merged {
+        if let Some(tracked) = state.quests.get(&event.quest.id) {
+            event.regions = tracked.regions_vec();
+            event.progress = tracked.progress();
+            if list_accounts {
+                event.accounts = tracked.accounts.iter().cloned().collect();
+            }
+        }
+    }
+    merged
 }
 
 #[must_use]
@@ -15,3 +116,93 @@ pub fn format_quest_message(quest: &DiscordQuest) -> String {
         .map_or("No reward", |r| r.messages.name.as_str());
     format!("Quest: {} - Reward: {}", quest.config.messages.game_title, reward_desc)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::quest;
+    use crate::types::quest::{QuestProgress, QuestUserStatus};
+
+    fn localized() -> DiscordQuest {
+        let mut quest = quest("1");
+        quest.config.messages.game_title = "Spiel".to_string();
+        quest
+    }
+
+    #[test]
+    fn localized_text_is_not_a_material_change() {
+        let mut localized = localized();
+        localized.config.rewards_config.rewards[0].messages.name = "700 Kugeln".to_string();
+        assert!(!has_material_change(&quest("1").config, &localized.config));
+    }
+
+    #[test]
+    fn dates_rewards_and_tasks_are_material_changes() {
+        let base = quest("1");
+
+        let mut extended = base.clone();
+        extended.config.expires_at = "2026-03-01T00:00:00+00:00".to_string();
+        assert!(has_material_change(&base.config, &extended.config));
+
+        let mut more_orbs = base.clone();
+        more_orbs.config.rewards_config.rewards[0].orb_quantity = Some(1000);
+        assert!(has_material_change(&base.config, &more_orbs.config));
+
+        let mut longer_task = base.clone();
+        longer_task.config.task_config.tasks.get_mut("WATCH_VIDEO").expect("task").target = 1800;
+        assert!(has_material_change(&base.config, &longer_task.config));
+    }
+
+    #[test]
+    fn known_quest_in_new_region_only_adds_the_region() {
+        let now = Utc::now();
+        let mut state = QuestState::default();
+
+        let created = apply_fetched_quests(&mut state, vec![quest("1")], "en-US", "main", now);
+        assert_eq!(created.len(), 1);
+        assert_eq!(created[0].event, QuestEventKind::Created);
+
+        let events = apply_fetched_quests(&mut state, vec![localized()], "de", "alt", now);
+        assert!(events.is_empty());
+        assert_eq!(state.quests["1"].regions_vec(), vec!["de".to_string(), "en-US".to_string()]);
+    }
+
+    #[test]
+    fn finalize_events_merges_fetches_and_lists_accounts() {
+        let now = Utc::now();
+        let mut state = QuestState::default();
+
+        let mut enrolled = quest("1");
+        enrolled.user_status = Some(QuestUserStatus {
+            enrolled_at: Some("2026-01-02T00:00:00+00:00".to_string()),
+            ..QuestUserStatus::default()
+        });
+        let mut events = apply_fetched_quests(&mut state, vec![quest("1")], "en-US", "main", now);
+        events.extend(apply_fetched_quests(&mut state, vec![enrolled], "de", "alt", now));
+        assert_eq!(events.len(), 1, "the second account only adds itself to the known quest");
+
+        let merged = finalize_events(&state, events.clone(), true);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].regions, vec!["de".to_string(), "en-US".to_string()]);
+        assert_eq!(merged[0].accounts, vec!["alt".to_string(), "main".to_string()]);
+        assert_eq!(
+            merged[0].progress,
+            BTreeMap::from([("alt".to_string(), QuestProgress::Enrolled), ("main".to_string(), QuestProgress::NotEnrolled)])
+        );
+        assert_eq!(merged[0].progress_summary().as_deref(), Some("alt: enrolled, main: not enrolled"));
+        assert!(state.quests["1"].quest.user_status.is_none(), "user status is stored per account");
+
+        assert!(finalize_events(&state, events, false)[0].accounts.is_empty());
+    }
+
+    #[test]
+    fn expire_quests_reports_each_quest_once() {
+        let mut state = QuestState::default();
+        let seen = DateTime::parse_from_rfc3339("2026-01-15T00:00:00+00:00").expect("date").with_timezone(&Utc);
+        apply_fetched_quests(&mut state, vec![quest("1")], "en-US", "main", seen);
+
+        let after_expiry = DateTime::parse_from_rfc3339("2026-02-02T00:00:00+00:00").expect("date").with_timezone(&Utc);
+        assert_eq!(expire_quests(&mut state, after_expiry).len(), 1);
+        assert!(expire_quests(&mut state, after_expiry).is_empty());
+    }
+}
@@ -0,0 +1,169 @@
+use crate::config::{PollingConfig, MAX_POLLING_INTERVAL_SEC, MIN_POLLING_INTERVAL_SEC};
+use crate::handlers::lookup::PollOutcome;
+use crate::metrics::metrics;
+use crate::types::error::AppError;
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+/// Longest run of failed polls that still doubles the back-off
+const MAX_BACKOFF_DOUBLINGS: u32 = 16;
+
+/// Decides how long the main loop waits before the next poll
+///
+/// The wait is `POLLING_INTERVAL_SEC`, or `LAUNCH_POLLING_INTERVAL_SEC` inside a launch
+/// window, or the time until `POLL_SCHEDULE` next fires. After polls in which a fetch
+/// failed it doubles per failed poll, up to `POLL_BACKOFF_MAX_SEC`, and it is never
+/// shorter than a `Retry-After` Discord sent. Jitter then varies interval waits by up to
+/// `POLL_JITTER_PERCENT`. Interval waits stay within the polling interval limits; the
+/// schedule itself keeps its polls far enough apart, so they run on time.
+#[derive(Debug, Default)]
+pub struct PollSchedule {
+    /// Polls in a row in which a fetch failed
+    failures: u32,
+    /// Longest `Retry-After` from the last poll
+    retry_after: Option<Duration>,
+}
+
+impl PollSchedule {
+    /// Update the back-off with a poll's outcome. Rejected tokens pause their account
+    /// instead, so they do not count as failures.
+    pub fn record(&mut self, outcome: &PollOutcome) {
+        let mut failed = false;
+        self.retry_after = None;
+        for (_, error) in &outcome.fetch_errors {
+            match error {
+                AppError::Unauthorized(_) => {}
+                AppError::RateLimited(_, retry_after) => {
+                    failed = true;
+                    self.retry_after = self.retry_after.max(*retry_after);
+                }
+                _ => failed = true,
+            }
+        }
+        if self.retry_after.is_some() {
+            metrics().rate_limit_waits.with_label_values(&["discord_api"]).inc();
+        }
+        self.failures = if failed { self.failures.saturating_add(1) } else { 0 };
+    }
+
+    /// Polls in a row in which a fetch failed
+    #[must_use]
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// The wait before the next poll, starting at `now`, for a regular `interval_sec`.
+    /// `jitter` is a random number in `-1.0..=1.0`.
+    #[must_use]
+    pub fn next_delay(&self, interval_sec: u64, polling: &PollingConfig, now: DateTime<Utc>, jitter: f64) -> Duration {
+        let (base, jitter_percent) = match &polling.schedule {
+            // Scheduled polls run on time, so they get no jitter
+            Some(schedule) => {
+                let next = schedule.next_after(now)
+                    .map_or(MAX_POLLING_INTERVAL_SEC as f64, |next| (next - now).num_milliseconds() as f64 / 1000.0);
+                (next, 0)
+            }
+            None if polling.launch_windows.iter().any(|w| w.contains(now)) => {
+                (polling.launch_interval_sec as f64, polling.jitter_percent)
+            }
+            None => (interval_sec as f64, polling.jitter_percent),
+        };
+        let secs = if self.failures == 0 {
+            base
+        } else {
+            let backoff = base * f64::from(1_u32 << self.failures.min(MAX_BACKOFF_DOUBLINGS));
+            backoff.min(polling.backoff_max_sec as f64).max(base)
+        };
+
+        let jittered = secs * (1.0 + jitter.clamp(-1.0, 1.0) * f64::from(jitter_percent) / 100.0);
+        let min_sec = if polling.schedule.is_some() { 0.0 } else { MIN_POLLING_INTERVAL_SEC as f64 };
+        let jittered = jittered.clamp(min_sec, MAX_POLLING_INTERVAL_SEC as f64);
+        // Whole milliseconds keep the waits readable in logs
+        let delay = Duration::from_millis((jittered * 1000.0).round() as u64);
+        match self.retry_after {
+            Some(retry_after) => delay.max(retry_after.min(Duration::from_secs(MAX_POLLING_INTERVAL_SEC))),
+            None => delay,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::polling::{CronSchedule, LaunchWindow};
+    use chrono::{NaiveTime, TimeZone};
+
+    fn polling() -> PollingConfig {
+        PollingConfig {
+            jitter_percent: 10,
+            backoff_max_sec: 1000,
+            launch_windows: vec![LaunchWindow {
+                days: None,
+                start: NaiveTime::from_hms_opt(15, 0, 0).unwrap(),
+                end: NaiveTime::from_hms_opt(19, 0, 0).unwrap(),
+            }],
+            launch_interval_sec: 60,
+            schedule: None,
+        }
+    }
+
+    fn failed(error: AppError) -> PollOutcome {
+        PollOutcome { fetch_errors: vec![("main".to_string(), error)], ..PollOutcome::default() }
+    }
+
+    #[test]
+    fn launch_windows_and_jitter_shape_the_wait() {
+        let schedule = PollSchedule::default();
+        let morning = Utc.with_ymd_and_hms(2026, 10, 12, 9, 0, 0).unwrap();
+        let launch = Utc.with_ymd_and_hms(2026, 10, 12, 16, 0, 0).unwrap();
+
+        assert_eq!(schedule.next_delay(300, &polling(), morning, 0.0), Duration::from_secs(300));
+        assert_eq!(schedule.next_delay(300, &polling(), launch, 0.0), Duration::from_secs(60));
+        assert_eq!(schedule.next_delay(300, &polling(), morning, 1.0), Duration::from_secs(330));
+        assert_eq!(schedule.next_delay(300, &polling(), morning, -1.0), Duration::from_secs(270));
+        assert_eq!(schedule.next_delay(30, &polling(), morning, -1.0), Duration::from_secs(30), "never below the minimum interval");
+    }
+
+    #[test]
+    fn failures_back_off_exponentially_until_a_poll_succeeds() {
+        let morning = Utc.with_ymd_and_hms(2026, 10, 12, 9, 0, 0).unwrap();
+        let mut schedule = PollSchedule::default();
+
+        schedule.record(&failed(AppError::Request("timeout".to_string())));
+        assert_eq!(schedule.next_delay(300, &polling(), morning, 0.0), Duration::from_secs(600));
+        schedule.record(&failed(AppError::Request("timeout".to_string())));
+        schedule.record(&failed(AppError::Request("timeout".to_string())));
+        assert_eq!(schedule.next_delay(300, &polling(), morning, 0.0), Duration::from_secs(1000), "capped by POLL_BACKOFF_MAX_SEC");
+
+        schedule.record(&failed(AppError::Unauthorized("401".to_string())));
+        assert_eq!(schedule.failures(), 0, "rejected tokens pause the account instead");
+        assert_eq!(schedule.next_delay(300, &polling(), morning, 0.0), Duration::from_secs(300));
+    }
+
+    #[test]
+    fn retry_after_is_respected() {
+        let launch = Utc.with_ymd_and_hms(2026, 10, 12, 16, 0, 0).unwrap();
+        let mut schedule = PollSchedule::default();
+        schedule.record(&failed(AppError::RateLimited("429".to_string(), Some(Duration::from_secs(500)))));
+        assert_eq!(schedule.next_delay(300, &polling(), launch, 0.0), Duration::from_secs(500));
+    }
+
+    #[test]
+    fn cron_schedule_waits_for_the_next_fire_without_jitter() {
+        let mut config = polling();
+        config.schedule = Some(CronSchedule {
+            expressions: vec!["0 */15 * * * *".parse().unwrap()],
+            timezone: chrono_tz::Tz::UTC,
+            longest_gap_sec: 900,
+        });
+        let now = Utc.with_ymd_and_hms(2026, 10, 12, 16, 0, 0).unwrap();
+        let mut schedule = PollSchedule::default();
+        assert_eq!(schedule.next_delay(300, &config, now, 1.0), Duration::from_secs(900), "the schedule replaces launch windows");
+
+        let just_before = now + chrono::TimeDelta::seconds(895);
+        assert_eq!(schedule.next_delay(300, &config, just_before, 0.0), Duration::from_secs(5), "not held back to the minimum interval");
+
+        schedule.record(&failed(AppError::Request("timeout".to_string())));
+        assert_eq!(schedule.next_delay(300, &config, now, 1.0), Duration::from_secs(1000));
+    }
+}
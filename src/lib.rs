@@ -1,6 +1,13 @@
+pub mod cli;
 pub mod communication;
 pub mod config;
+pub mod export;
 pub mod handlers;
+pub mod metrics;
+pub mod server;
 pub mod shutdown;
+pub mod systemd;
+#[cfg(test)]
+mod test_support;
 pub mod types;
 pub mod utils;
@@ -1,82 +1,340 @@
+mod cli;
 mod config;
 mod types;
 mod utils;
 mod communication;
+mod export;
 mod handlers;
+mod metrics;
+mod server;
 mod shutdown;
+mod systemd;
+#[cfg(test)]
+mod test_support;
 
+use std::process::ExitCode;
 use std::sync::{Arc, RwLock};
-use std::collections::HashSet;
 use std::time::Duration;
 use tokio::time;
-use tokio::sync::{oneshot, broadcast};
-use tracing::{error, info};
+use tokio::sync::{broadcast, mpsc, watch};
+use clap::Parser;
+use tracing::{error, info, info_span, warn, Instrument};
+use cli::Command;
+use communication::notifier::Notifier;
+use handlers::commands;
+use types::error::AppError;
 
 #[tokio::main]
-async fn main() {
-    utils::setup_logging();
+async fn main() -> ExitCode {
+    let cli = cli::Cli::parse();
+    let command = cli.command();
+    let dry_run = cli.dry_run();
+    let command_mode = !matches!(command, Command::Run | Command::Once);
+    // Dry runs print notifications on stdout, so the daemon's logs move to stderr
+    let log_stderr = command_mode || dry_run == Some(config::DryRunOutput::Stdout);
 
-    let config = match config::AppConfig::load() {
+    // Read-only commands work without any notification destination configured
+    let location = cli.config_location();
+    let loaded = match command {
+        Command::List { .. } | Command::Show { .. } | Command::Preview { .. } => {
+            config::AppConfig::load_without_destinations(&location)
+        }
+        _ => config::AppConfig::load(&location),
+    };
+    // Logging is configured by the config, so a config error is logged with the defaults
+    let logging = loaded.as_ref().map(|c| c.logging.clone()).unwrap_or_default();
+    if let Err(e) = utils::setup_logging(&logging, command_mode, log_stderr) {
+        eprintln!("{e}");
+        return ExitCode::FAILURE;
+    }
+    let mut config = match loaded {
         Ok(c) => c,
         Err(e) => {
             error!("Failed to load config: {}", e);
-            return;
+            return ExitCode::FAILURE;
         }
     };
 
-    // Setup shutdown channels
-    let (shutdown_tx, mut shutdown_rx) = oneshot::channel();
-    let (component_shutdown_tx, _component_shutdown_rx) = oneshot::channel();
-    
-    // Broadcast channel for graceful shutdown to all tasks
-    let (broadcast_shutdown_tx, _) = broadcast::channel::<()>(1);
-    let broadcast_tx_clone = broadcast_shutdown_tx.clone();
+    if dry_run.is_some() && command_mode {
+        error!("--dry-run and --dry-run-dir only apply to run and once");
+        return ExitCode::FAILURE;
+    }
+    config.dry_run = dry_run;
 
-    // Spawn signal handler
-    tokio::spawn(async move {
-        shutdown::handle_signals(shutdown_tx, component_shutdown_tx).await;
-        // Broadcast shutdown to all tasks
-        let _ = broadcast_tx_clone.send(());
-    });
+    if !command_mode {
+        if let Err(e) = handlers::preflight::check(&config).await {
+            error!("{}", e);
+            return match e {
+                AppError::Unauthorized(_) if command == Command::Once => {
+                    ExitCode::from(commands::OnceStatus::Unauthorized.exit_code())
+                }
+                _ => ExitCode::FAILURE,
+            };
+        }
+    }
 
-    let state = Arc::new(RwLock::new(HashSet::new()));
-    let mut interval = time::interval(Duration::from_secs(config.polling_interval_sec));
+    let result = match command {
+        Command::Run => run(config).await,
+        Command::Once => {
+            return match commands::once(&config).await {
+                Ok(status) => ExitCode::from(status.exit_code()),
+                Err(e) => {
+                    error!("{}", e);
+                    ExitCode::FAILURE
+                }
+            };
+        }
+        Command::List { json, region, account } => {
+            commands::list(&config, account.as_deref(), region.as_deref(), json).await
+        }
+        Command::Show { quest_id, json, region, account } => {
+            commands::show(&config, &quest_id, account.as_deref(), region.as_deref(), json).await
+        }
+        Command::Preview { quest_id, region, account } => {
+            commands::preview(&config, &quest_id, account.as_deref(), region.as_deref()).await
+        }
+        Command::TestWebhook => commands::test_webhook(&config).await,
+        Command::ValidateConfig => {
+            commands::validate_config(&config);
+            Ok(())
+        }
+        Command::PrintSystemdUnit => commands::print_systemd_unit(&config),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Poll for quests until a shutdown signal is received
+async fn run(config: config::AppConfig) -> Result<(), AppError> {
+    // Broadcast channel for graceful shutdown to all tasks; a second signal exits at once
+    let (broadcast_shutdown_tx, mut shutdown_rx) = broadcast::channel::<()>(1);
+    tokio::spawn(shutdown::handle_signals(broadcast_shutdown_tx.clone()));
+
+    // Quests remembered from a previous run are not re-announced
+    let saved_state = handlers::lookup::load_state(&config)?;
+    let mut is_initial_run = saved_state.is_none();
+    let state = Arc::new(RwLock::new(saved_state.unwrap_or_default()));
+    let mut notifier = Notifier::new(&config)?;
+    let mut notifier_mqtt = config.mqtt.clone();
+    // The first poll starts right away; the schedule decides the wait before each later one
+    let next_poll = time::sleep(Duration::ZERO);
+    tokio::pin!(next_poll);
+    let mut schedule = handlers::schedule::PollSchedule::default();
+    let mut polling_settings = (config.polling_interval_sec, config.polling.clone());
+    // Digests run on their own schedule, independent of the polls
+    let mut digest_at = next_digest_at(&config, chrono::Utc::now());
+    let next_digest = time::sleep_until(until(digest_at));
+    tokio::pin!(next_digest);
+    let mut digest_settings = config.digest.clone();
+    let status = Arc::new(RwLock::new(handlers::health::RuntimeStatus::new(&config, chrono::Utc::now())));
+    if let Some(addr) = config.http_addr {
+        let listener = server::bind(addr).await?;
+        tokio::spawn(server::serve(listener, status.clone(), broadcast_shutdown_tx.subscribe()));
+    }
+
+    // Hot reload: config file changes and SIGHUP swap the active config in place
+    let (config_tx, mut config_rx) = watch::channel(Arc::new(config));
+    let (reload_tx, reload_rx) = mpsc::channel(1);
+    tokio::spawn(shutdown::handle_reload_signals(reload_tx));
+    tokio::spawn(config::reload::watch_config(config_tx, reload_rx, broadcast_shutdown_tx.subscribe()));
 
     info!("Starting Discord Quest Notification...");
     info!("Press Ctrl+C to shutdown gracefully");
 
-    let mut is_initial_run = true;
-    let mut region_index = 0;
+    let mut rotation = handlers::lookup::RegionRotation::default();
+    let mut token_guard = handlers::auth::TokenGuard::default();
+    let mut ops_monitor = handlers::monitor::OpsMonitor::default();
+    // Readiness and status for systemd. The watchdog is pinged while the loop is idle, and
+    // every await that can take long inside the loop (the poll, alerts, the digest, saving
+    // the state and reconnecting MQTT) runs through the watchdog so pings go on meanwhile.
+    let mut service = systemd::ServiceNotifier::default();
+    let mut watchdog = systemd::Watchdog::new();
 
     loop {
         tokio::select! {
-            _ = interval.tick() => {
+            // A pending shutdown wins over a due poll
+            biased;
+            _ = shutdown_rx.recv() => {
+                info!("Shutdown signal received, exiting main loop");
+                service.stopping();
+                break;
+            }
+            () = watchdog.ping_when_due() => {}
+            () = &mut next_poll => {
+                let config = config_rx.borrow_and_update().clone();
+                let (started, started_at) = (time::Instant::now(), chrono::Utc::now());
+                next_poll.as_mut().reset(started + next_poll_delay(&schedule, &config, started_at));
+                let polls: Vec<handlers::lookup::AccountPoll> = config.accounts.iter()
+                    .filter(|account| !token_guard.is_paused(account))
+                    .map(|account| handlers::lookup::AccountPoll { account, region: rotation.current(account) })
+                    .collect();
+                if polls.is_empty() {
+                    continue;
+                }
                 let state_clone = state.clone();
                 let shutdown_receiver = broadcast_shutdown_tx.subscribe();
-                
-                let current_region = if config.discord_regions.is_empty() {
-                    "en-US"
-                } else {
-                    &config.discord_regions[region_index]
-                };
+                let mut poll_shutdown_rx = broadcast_shutdown_tx.subscribe();
 
-                info!("Checking quests for region: {}", current_region);
+                for poll in &polls {
+                    info!("Checking quests for account {} in region: {}", poll.account.name, poll.region);
+                }
 
-                if let Err(e) = handlers::lookup::app(&config, state_clone, is_initial_run, current_region, shutdown_receiver).await {
-                    error!("Error in app (region: {}): {}", current_region, e);
+                let poll = handlers::lookup::app(&config, &notifier, state_clone, is_initial_run, &polls, shutdown_receiver)
+                    .instrument(info_span!("poll", initial = is_initial_run));
+                tokio::pin!(poll);
+                // On shutdown the poll stops after the notification being sent, within a deadline.
+                // The watchdog is still pinged while the poll waits on Discord or a destination.
+                let result = loop {
+                    tokio::select! {
+                        result = &mut poll => break Some(result),
+                        _ = poll_shutdown_rx.recv() => {
+                            info!("Waiting up to {}s for the notifications being sent", config.shutdown_timeout_sec);
+                            service.stopping();
+                            break time::timeout(Duration::from_secs(config.shutdown_timeout_sec), &mut poll).await.ok();
+                        }
+                        () = watchdog.ping_when_due() => {}
+                    }
+                };
+                let outcome = match result {
+                    Some(Ok(outcome)) => outcome,
+                    Some(Err(e)) => {
+                        error!("Error in app: {}", e);
+                        continue;
+                    }
+                    None => {
+                        warn!("Sending did not finish within {}s; unsent notifications are sent after a restart", config.shutdown_timeout_sec);
+                        break;
+                    }
+                };
+
+                for (name, e) in &outcome.fetch_errors {
+                    let Some(account) = config.accounts.iter().find(|a| &a.name == name) else { continue };
+                    match e {
+                        AppError::Unauthorized(reason) => {
+                            error!("Discord rejected the token of account {}: {}. Polling it is paused until its token is replaced.", name, reason);
+                            if token_guard.reject(account) {
+                                watchdog.keep_alive(notifier.alert(&config, &handlers::auth::token_rejected_alert(name, reason))).await;
+                            }
+                        }
+                        e => error!("Error in app (account: {}, region: {}): {}", name, rotation.current(account), e),
+                    }
+                }
+                schedule.record(&outcome);
+                let delay = next_poll_delay(&schedule, &config, started_at);
+                if schedule.failures() > 0 {
+                    info!("Backing off after {} failed poll(s), next poll in {}s", schedule.failures(), delay.as_secs());
+                }
+                next_poll.as_mut().reset(started + delay);
+                for alert in ops_monitor.record(&config.alerts, &polls, &outcome) {
+                    watchdog.keep_alive(notifier.alert(&config, &alert)).await;
+                }
+                if let (Ok(mut status), Ok(quest_state)) = (status.write(), state.read()) {
+                    status.record_poll(&polls, &outcome, &quest_state, chrono::Utc::now());
+                    status.paused_accounts = token_guard.paused_accounts();
+                }
+                service.poll_finished(&outcome, chrono::Utc::now());
+                for poll in &polls {
+                    rotation.advance(poll.account);
                 }
-                
+                if outcome.polled == 0 {
+                    continue;
+                }
+                if let Err(e) = watchdog.keep_alive(handlers::lookup::save_state(&config, &state)).await {
+                    error!("Failed to save quest state: {}", e);
+                }
+
                 is_initial_run = false;
-                
-                if !config.discord_regions.is_empty() {
-                    region_index = (region_index + 1) % config.discord_regions.len();
+
+                if rotation.all_cycled(&config) {
+                    if let Err(e) = handlers::lookup::clear_stale_state(&notifier, &state) {
+                        error!("Failed to clear stale quest state: {}", e);
+                    }
                 }
             }
-            _ = &mut shutdown_rx => {
-                info!("Shutdown signal received, exiting main loop");
-                break;
+            () = &mut next_digest, if digest_at.is_some() => {
+                let config = config_rx.borrow().clone();
+                // The scheduled time, so the timer may wake a little early or late
+                let now = digest_at.unwrap_or_else(chrono::Utc::now);
+                if is_initial_run {
+                    info!("Skipping the quest digest: no poll has completed yet");
+                } else if let Err(e) = watchdog.keep_alive(
+                    handlers::lookup::send_digest(&config, &notifier, &state, now).instrument(info_span!("digest"))
+                ).await {
+                    error!("Failed to send the quest digest: {}", e);
+                }
+                digest_at = next_digest_at(&config, now);
+                next_digest.as_mut().reset(until(digest_at));
+            }
+            Ok(()) = config_rx.changed() => {
+                let config = config_rx.borrow().clone();
+                if config.mqtt != notifier_mqtt {
+                    // Disconnect first: the new client reuses the client ID, and a takeover
+                    // would make the broker publish the old connection's `offline` last will
+                    watchdog.keep_alive(std::mem::replace(&mut notifier, Notifier::without_mqtt()).shutdown()).await;
+                    notifier = Notifier::new(&config).unwrap_or_else(|e| {
+                        error!("Failed to reconnect MQTT with the reloaded config: {}", e);
+                        Notifier::without_mqtt()
+                    });
+                    notifier_mqtt.clone_from(&config.mqtt);
+                }
+                let settings = (config.polling_interval_sec, config.polling.clone());
+                if settings != polling_settings {
+                    polling_settings = settings;
+                    next_poll.as_mut().reset(time::Instant::now() + next_poll_delay(&schedule, &config, chrono::Utc::now()));
+                }
+                if config.digest != digest_settings {
+                    digest_settings.clone_from(&config.digest);
+                    digest_at = next_digest_at(&config, chrono::Utc::now());
+                    next_digest.as_mut().reset(until(digest_at));
+                }
+                rotation.retain(&config);
+                ops_monitor.retain(&config);
+                let resumed = token_guard.resume_if_replaced(&config.accounts);
+                if let Ok(mut status) = status.write() {
+                    status.apply_config(&config);
+                    status.paused_accounts = token_guard.paused_accounts();
+                }
+                if !resumed.is_empty() {
+                    info!("Token replaced for account(s) {}, resuming polling", resumed.join(", "));
+                    next_poll.as_mut().reset(time::Instant::now());
+                }
             }
         }
     }
+
+    // Keep what this run learned, including notifications that are still unsent
+    let config = config_rx.borrow().clone();
+    if let Err(e) = handlers::lookup::save_state(&config, &state).await {
+        error!("Failed to save quest state: {}", e);
+    }
+    notifier.shutdown().await;
     info!("Application exited gracefully");
+    Ok(())
+}
+
+/// Wait before the next poll after one that started at `now`, with fresh jitter
+fn next_poll_delay(
+    schedule: &handlers::schedule::PollSchedule,
+    config: &config::AppConfig,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Duration {
+    schedule.next_delay(config.polling_interval_sec, &config.polling, now, fastrand::f64() * 2.0 - 1.0)
+}
+
+/// When the next scheduled digest is due, or `None` without `DIGEST_URLS`
+fn next_digest_at(config: &config::AppConfig, now: chrono::DateTime<chrono::Utc>) -> Option<chrono::DateTime<chrono::Utc>> {
+    config.digest.as_ref()?.schedule.next_after(now)
+}
+
+/// Deadline of a timer firing at `at`; without a time the timer is disabled by its
+/// `select!` guard, so any deadline works
+fn until(at: Option<chrono::DateTime<chrono::Utc>>) -> time::Instant {
+    let wait = at.and_then(|at| (at - chrono::Utc::now()).to_std().ok()).unwrap_or_default();
+    time::Instant::now() + wait
 }
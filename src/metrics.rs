@@ -0,0 +1,93 @@
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use std::sync::LazyLock;
+
+/// Latency buckets in seconds, from fast API calls to slow retried webhooks
+const LATENCY_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+/// Prometheus metrics of the monitor, served on `/metrics` when `HTTP_ADDR` is set
+pub struct Metrics {
+    registry: Registry,
+    /// Quest fetches per account and region
+    pub polls: IntCounterVec,
+    /// Failed quest fetches by HTTP status, or `network` / `invalid_response`
+    pub fetch_errors: IntCounterVec,
+    /// Quests seen for the first time
+    pub quests_discovered: IntCounter,
+    /// Deliveries per destination and result (`sent` or `failed`)
+    pub notifications: IntCounterVec,
+    /// Waits caused by a 429 response, per target
+    pub rate_limit_waits: IntCounterVec,
+    /// Discord API request latency per endpoint
+    pub api_latency: HistogramVec,
+    /// Discord webhook request latency
+    pub webhook_latency: Histogram,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let counter = |name: &str, help: &str, labels: &[&str]| {
+            let metric = IntCounterVec::new(Opts::new(name, help), labels).expect("valid metric");
+            registry.register(Box::new(metric.clone())).expect("unique metric name");
+            metric
+        };
+        let histogram = |name: &str, help: &str| HistogramOpts::new(name, help).buckets(LATENCY_BUCKETS.to_vec());
+
+        let polls = counter("discord_quest_polls_total", "Quest fetches per account and region", &["account", "region"]);
+        let fetch_errors = counter("discord_quest_fetch_errors_total", "Failed quest fetches by status", &["status"]);
+        let notifications = counter("discord_quest_notifications_total", "Deliveries per destination and result", &["destination", "result"]);
+        let rate_limit_waits = counter("discord_quest_rate_limit_waits_total", "Waits caused by rate limiting", &["target"]);
+
+        let quests_discovered = IntCounter::new("discord_quest_quests_discovered_total", "Quests seen for the first time")
+            .expect("valid metric");
+        registry.register(Box::new(quests_discovered.clone())).expect("unique metric name");
+
+        let api_latency = HistogramVec::new(
+            histogram("discord_quest_api_request_duration_seconds", "Discord API request latency"),
+            &["endpoint"],
+        ).expect("valid metric");
+        registry.register(Box::new(api_latency.clone())).expect("unique metric name");
+
+        let webhook_latency = Histogram::with_opts(
+            histogram("discord_quest_webhook_request_duration_seconds", "Discord webhook request latency"),
+        ).expect("valid metric");
+        registry.register(Box::new(webhook_latency.clone())).expect("unique metric name");
+
+        Self { registry, polls, fetch_errors, quests_discovered, notifications, rate_limit_waits, api_latency, webhook_latency }
+    }
+}
+
+/// The process-wide metrics
+#[must_use]
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+/// Render every metric in the Prometheus text format
+#[must_use]
+pub fn encode() -> String {
+    let mut buffer = Vec::new();
+    // Encoding into a Vec only fails for invalid metric families, which the registry rejects
+    let _ = TextEncoder::new().encode(&metrics().registry.gather(), &mut buffer);
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_includes_recorded_metrics() {
+        metrics().polls.with_label_values(&["main", "en-US"]).inc();
+        metrics().webhook_latency.observe(0.2);
+
+        let text = encode();
+        assert!(text.contains(r#"discord_quest_polls_total{account="main",region="en-US"}"#), "{text}");
+        assert!(text.contains("discord_quest_webhook_request_duration_seconds_bucket"), "{text}");
+        assert!(text.contains("discord_quest_quests_discovered_total 0"), "{text}");
+    }
+}
@@ -0,0 +1,77 @@
+use crate::handlers::health::SharedStatus;
+use crate::metrics;
+use crate::types::error::AppError;
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::Utc;
+use serde_json::json;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+use tracing::{error, info};
+
+/// Content type of the Prometheus text exposition format
+const METRICS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Bind the HTTP listener, so a port that is already in use fails startup
+///
+/// # Errors
+///
+/// Returns `AppError::Io` if the address cannot be bound
+pub async fn bind(addr: SocketAddr) -> Result<TcpListener, AppError> {
+    TcpListener::bind(addr).await
+        .map_err(|e| AppError::Io(format!("Failed to listen on HTTP_ADDR {addr}: {e}")))
+}
+
+/// Serve `/metrics`, `/healthz`, `/readyz` and `/status` until a shutdown signal is received
+pub async fn serve(listener: TcpListener, status: SharedStatus, mut shutdown_rx: broadcast::Receiver<()>) {
+    let app = Router::new()
+        .route("/metrics", get(metrics_handler))
+        .route("/healthz", get(|| async { "ok" }))
+        .route("/readyz", get(readyz))
+        .route("/status", get(status_handler))
+        .with_state(status);
+
+    if let Ok(addr) = listener.local_addr() {
+        info!("Serving metrics and health checks on http://{}", addr);
+    }
+    let result = axum::serve(listener, app)
+        .with_graceful_shutdown(async move {
+            let _ = shutdown_rx.recv().await;
+        })
+        .await;
+    if let Err(e) = result {
+        error!("HTTP listener failed: {}", e);
+    }
+}
+
+async fn metrics_handler() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, METRICS_CONTENT_TYPE)], metrics::encode())
+}
+
+/// 200 with `ok`, or 503 listing why the monitor is not ready
+async fn readyz(State(status): State<SharedStatus>) -> Response {
+    let problems = match status.read() {
+        Ok(status) => status.problems(Utc::now()),
+        Err(_) => vec!["status lock poisoned".to_string()],
+    };
+    if problems.is_empty() {
+        "ok".into_response()
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, problems.join("\n")).into_response()
+    }
+}
+
+async fn status_handler(State(status): State<SharedStatus>) -> Response {
+    let Ok(status) = status.read() else {
+        return (StatusCode::INTERNAL_SERVER_ERROR, "status lock poisoned").into_response();
+    };
+    let problems = status.problems(Utc::now());
+    let mut body = json!(&*status);
+    body["ready"] = json!(problems.is_empty());
+    body["problems"] = json!(problems);
+    Json(body).into_response()
+}
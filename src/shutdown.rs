@@ -1,79 +1,119 @@
-use tokio::sync::oneshot;
-use tracing::{error, info};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{error, info, warn};
 
 #[cfg(unix)]
-use tokio::signal::unix::{signal, SignalKind};
+use tokio::signal::unix::{signal, Signal, SignalKind};
 #[cfg(windows)]
-use tokio::signal::windows::{ctrl_break, ctrl_c};
+use tokio::signal::windows::{ctrl_break, ctrl_c, CtrlBreak, CtrlC};
 
-pub async fn handle_signals(
-    shutdown_tx: oneshot::Sender<()>,
-    shutdown_components: oneshot::Sender<()>,
-) {
-    wait_for_signal().await;
-
-    if let Err(e) = shutdown_components.send(()) {
-        error!("Failed to send component shutdown signal: {:?}", e);
-    }
+/// Request a graceful shutdown through `shutdown_tx` on the first SIGINT or SIGTERM
+/// (Ctrl+C or Ctrl+Break on Windows), and exit immediately on the second one
+pub async fn handle_signals(shutdown_tx: broadcast::Sender<()>) {
+    let Some(mut signals) = ShutdownSignals::new() else { return };
+    let name = signals.recv().await;
+    info!("Received {} signal, initiating graceful shutdown (send it again to exit immediately)", name);
 
     if let Err(e) = shutdown_tx.send(()) {
-        error!("Failed to send main shutdown signal: {:?}", e);
+        error!("Failed to send shutdown signal: {:?}", e);
     }
+
+    let name = signals.recv().await;
+    warn!("Received {} during shutdown, exiting without waiting for in-flight deliveries", name);
+    std::process::exit(1);
 }
 
+/// Forward reload requests (SIGHUP) to `reload_tx` until the receiver is dropped
 #[cfg(unix)]
-async fn wait_for_signal() {
-    let mut sigterm = match signal(SignalKind::terminate()) {
+pub async fn handle_reload_signals(reload_tx: mpsc::Sender<()>) {
+    let mut sighup = match signal(SignalKind::hangup()) {
         Ok(s) => s,
         Err(e) => {
-            error!("Failed to create SIGTERM signal handler: {}", e);
+            error!("Failed to create SIGHUP signal handler: {}", e);
             return;
         }
     };
-    // Handle SIGINT (Ctrl+C)
-    let mut sigint = match signal(SignalKind::interrupt()) {
-        Ok(s) => s,
-        Err(e) => {
-            error!("Failed to create SIGINT signal handler: {}", e);
+
+    while sighup.recv().await.is_some() {
+        info!("Received SIGHUP signal, reloading configuration");
+        if reload_tx.send(()).await.is_err() {
             return;
         }
-    };
+    }
+}
 
-    tokio::select! {
-        _ = sigterm.recv() => {
-            info!("Received SIGTERM signal, initiating graceful shutdown");
-        }
-        _ = sigint.recv() => {
-            info!("Received SIGINT signal, initiating graceful shutdown");
+/// Reload signals are not available on this platform; config file changes are still picked up
+#[cfg(not(unix))]
+pub async fn handle_reload_signals(_reload_tx: mpsc::Sender<()>) {}
+
+#[cfg(unix)]
+struct ShutdownSignals {
+    sigterm: Signal,
+    sigint: Signal,
+}
+
+#[cfg(unix)]
+impl ShutdownSignals {
+    fn new() -> Option<Self> {
+        let sigterm = match signal(SignalKind::terminate()) {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Failed to create SIGTERM signal handler: {}", e);
+                return None;
+            }
+        };
+        // Handle SIGINT (Ctrl+C)
+        let sigint = match signal(SignalKind::interrupt()) {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Failed to create SIGINT signal handler: {}", e);
+                return None;
+            }
+        };
+        Some(Self { sigterm, sigint })
+    }
+
+    /// Wait for the next signal and return its name
+    async fn recv(&mut self) -> &'static str {
+        tokio::select! {
+            _ = self.sigterm.recv() => "SIGTERM",
+            _ = self.sigint.recv() => "SIGINT",
         }
     }
 }
 
 #[cfg(windows)]
-async fn wait_for_signal() {
-    // Handle Ctrl+C
-    let mut ctrlc = match ctrl_c() {
-        Ok(s) => s,
-        Err(e) => {
-            error!("Failed to create Ctrl+C signal handler: {}", e);
-            return;
-        }
-    };
-    // Handle Ctrl+Break
-    let mut ctrlbreak = match ctrl_break() {
-        Ok(s) => s,
-        Err(e) => {
-            error!("Failed to create Ctrl+Break signal handler: {}", e);
-            return;
-        }
-    };
+struct ShutdownSignals {
+    ctrlc: CtrlC,
+    ctrlbreak: CtrlBreak,
+}
 
-    tokio::select! {
-        _ = ctrlc.recv() => {
-            info!("Received Ctrl+C signal, initiating graceful shutdown");
-        }
-        _ = ctrlbreak.recv() => {
-            info!("Received Ctrl+Break signal, initiating graceful shutdown");
+#[cfg(windows)]
+impl ShutdownSignals {
+    fn new() -> Option<Self> {
+        // Handle Ctrl+C
+        let ctrlc = match ctrl_c() {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Failed to create Ctrl+C signal handler: {}", e);
+                return None;
+            }
+        };
+        // Handle Ctrl+Break
+        let ctrlbreak = match ctrl_break() {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Failed to create Ctrl+Break signal handler: {}", e);
+                return None;
+            }
+        };
+        Some(Self { ctrlc, ctrlbreak })
+    }
+
+    /// Wait for the next signal and return its name
+    async fn recv(&mut self) -> &'static str {
+        tokio::select! {
+            _ = self.ctrlc.recv() => "Ctrl+C",
+            _ = self.ctrlbreak.recv() => "Ctrl+Break",
         }
     }
 }
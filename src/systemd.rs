@@ -0,0 +1,257 @@
+use crate::config::ConfigLocation;
+use crate::handlers::lookup::PollOutcome;
+use chrono::{DateTime, Utc};
+use std::fmt::Write as FmtWrite;
+use std::future::Future;
+use std::path::Path;
+use std::time::Duration;
+use tokio::time;
+use tracing::{debug, info};
+
+#[cfg(unix)]
+use sd_notify::NotifyState;
+
+/// Readiness and status notifications for running as a `Type=notify` systemd service
+///
+/// Outside systemd (`NOTIFY_SOCKET` unset) every notification is a no-op.
+#[derive(Debug, Default)]
+pub struct ServiceNotifier {
+    ready: bool,
+}
+
+impl ServiceNotifier {
+    /// Report a finished poll as the service status, and signal readiness after the
+    /// first poll that fetched any account's quests
+    pub fn poll_finished(&mut self, outcome: &PollOutcome, now: DateTime<Utc>) {
+        let status = poll_status(outcome, now);
+        if !self.ready && outcome.polled > 0 {
+            self.ready = true;
+            notify(&[NotifyState::Ready, NotifyState::Status(&status)]);
+        } else {
+            notify(&[NotifyState::Status(&status)]);
+        }
+    }
+
+    /// Report that a graceful shutdown has started
+    pub fn stopping(&self) {
+        notify(&[NotifyState::Stopping, NotifyState::Status("Shutting down")]);
+    }
+}
+
+/// Pings the systemd watchdog at half of the unit's `WatchdogSec`, telling it the main
+/// loop is still running
+///
+/// Without a watchdog for this process the pings never become due.
+#[derive(Debug)]
+pub struct Watchdog {
+    ticks: Option<time::Interval>,
+}
+
+impl Watchdog {
+    /// The watchdog of this process, if its unit enables one
+    #[must_use]
+    pub fn new() -> Self {
+        let interval = watchdog_usec().map(|usec| Duration::from_micros(usec) / 2);
+        if let Some(interval) = interval {
+            info!("systemd watchdog enabled, pinging every {}ms", interval.as_millis());
+        }
+        Self::every(interval)
+    }
+
+    fn every(interval: Option<Duration>) -> Self {
+        let ticks = interval.map(|interval| {
+            let mut ticks = time::interval(interval);
+            ticks.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+            ticks
+        });
+        Self { ticks }
+    }
+
+    /// Wait until the next ping is due and send it
+    pub async fn ping_when_due(&mut self) {
+        match &mut self.ticks {
+            Some(ticks) => {
+                ticks.tick().await;
+                notify(&[NotifyState::Watchdog]);
+            }
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Await `task`, pinging the watchdog whenever a ping is due in the meantime
+    pub async fn keep_alive<F: Future>(&mut self, task: F) -> F::Output {
+        tokio::pin!(task);
+        loop {
+            tokio::select! {
+                output = &mut task => return output,
+                () = self.ping_when_due() => {}
+            }
+        }
+    }
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// One-line summary of a poll for `systemctl status`
+#[must_use]
+pub fn poll_status(outcome: &PollOutcome, now: DateTime<Utc>) -> String {
+    let mut status = format!("Last poll {}: {} account(s) polled", now.format("%Y-%m-%d %H:%M:%S UTC"), outcome.polled);
+    if !outcome.fetch_errors.is_empty() {
+        let _ = write!(status, ", {} failed", outcome.fetch_errors.len());
+    }
+    let _ = write!(status, ", {} new event(s), {} delivered", outcome.events, outcome.delivered);
+    if outcome.failed > 0 {
+        let _ = write!(status, ", {} queued for retry", outcome.failed);
+    }
+    status
+}
+
+/// Render a `Type=notify` unit that runs `exe` from `working_dir`, where `.env` is read
+/// from, with the config file and profile of `location`
+#[must_use]
+pub fn unit_file(exe: &Path, working_dir: &Path, location: &ConfigLocation, shutdown_timeout_sec: u64) -> String {
+    let mut exec_start = exec_arg(&exe.display().to_string());
+    if let Some(path) = &location.path {
+        let _ = write!(exec_start, " --config {}", exec_arg(&working_dir.join(path).display().to_string()));
+    }
+    if let Some(profile) = &location.profile {
+        let _ = write!(exec_start, " --profile {}", exec_arg(profile));
+    }
+    // Leave the deadline of the graceful shutdown some room before systemd kills the process
+    let stop_timeout_sec = shutdown_timeout_sec + 5;
+    let working_dir = working_dir.display().to_string().replace('%', "%%");
+
+    format!(
+        "[Unit]
+Description=Discord Quest Notification
+Wants=network-online.target
+After=network-online.target
+
+[Service]
+Type=notify
+ExecStart={exec_start} run
+ExecReload=/bin/kill -HUP $MAINPID
+WorkingDirectory={working_dir}
+WatchdogSec=60
+TimeoutStopSec={stop_timeout_sec}
+Restart=on-failure
+RestartSec=30
+NoNewPrivileges=true
+PrivateTmp=true
+
+[Install]
+WantedBy=multi-user.target
+"
+    )
+}
+
+/// Quote a command line argument for `ExecStart=`, where `%` starts a specifier and
+/// `$` an environment variable
+fn exec_arg(arg: &str) -> String {
+    let escaped = arg.replace('%', "%%").replace('$', "$$");
+    if escaped.is_empty() || escaped.contains(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | ';')) {
+        format!("\"{}\"", escaped.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        escaped
+    }
+}
+
+#[cfg(unix)]
+fn notify(states: &[NotifyState]) {
+    if let Err(e) = sd_notify::notify(false, states) {
+        debug!("Failed to notify systemd: {}", e);
+    }
+}
+
+#[cfg(unix)]
+fn watchdog_usec() -> Option<u64> {
+    let mut usec = 0;
+    sd_notify::watchdog_enabled(false, &mut usec).then_some(usec).filter(|&usec| usec > 0)
+}
+
+/// systemd notifications are not available on this platform
+#[cfg(not(unix))]
+enum NotifyState<'a> {
+    Ready,
+    Status(&'a str),
+    Watchdog,
+    Stopping,
+}
+
+#[cfg(not(unix))]
+fn notify(_states: &[NotifyState]) {
+    debug!("systemd notifications are not supported on this platform");
+}
+
+#[cfg(not(unix))]
+fn watchdog_usec() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::error::AppError;
+    use chrono::TimeZone;
+
+    #[test]
+    fn poll_status_summarises_the_outcome() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 18, 9, 30, 0).unwrap();
+        let quiet = PollOutcome { polled: 2, ..PollOutcome::default() };
+        assert_eq!(poll_status(&quiet, now), "Last poll 2026-10-18 09:30:00 UTC: 2 account(s) polled, 0 new event(s), 0 delivered");
+
+        let troubled = PollOutcome {
+            polled: 1,
+            fetch_errors: vec![("alt".to_string(), AppError::Request("timeout".to_string()))],
+            events: 3,
+            delivered: 2,
+            failed: 1,
+            ..PollOutcome::default()
+        };
+        assert_eq!(
+            poll_status(&troubled, now),
+            "Last poll 2026-10-18 09:30:00 UTC: 1 account(s) polled, 1 failed, 3 new event(s), 2 delivered, 1 queued for retry"
+        );
+    }
+
+    #[tokio::test]
+    async fn watchdog_keeps_alive_until_the_task_finishes() {
+        let mut watchdog = Watchdog::every(Some(Duration::from_millis(5)));
+        let output = watchdog.keep_alive(async {
+            time::sleep(Duration::from_millis(30)).await;
+            7
+        }).await;
+        assert_eq!(output, 7);
+
+        let mut disabled = Watchdog::every(None);
+        assert!(time::timeout(Duration::from_millis(20), disabled.ping_when_due()).await.is_err(), "no pings without a watchdog");
+        assert_eq!(disabled.keep_alive(async { 7 }).await, 7);
+    }
+
+    #[test]
+    fn exec_args_are_quoted_only_when_needed() {
+        assert_eq!(exec_arg("/usr/local/bin/discord"), "/usr/local/bin/discord");
+        assert_eq!(exec_arg("/opt/quest bot/config.toml"), "\"/opt/quest bot/config.toml\"");
+        assert_eq!(exec_arg("100%"), "100%%");
+        assert_eq!(exec_arg("a\"b c"), "\"a\\\"b c\"");
+        assert_eq!(exec_arg(""), "\"\"");
+        assert_eq!(exec_arg("$HOME"), "$$HOME");
+    }
+
+    #[test]
+    fn unit_runs_the_daemon_with_the_config_file() {
+        let location = ConfigLocation { path: Some("config.toml".into()), profile: Some("prod".to_string()) };
+        let unit = unit_file(Path::new("/usr/local/bin/discord"), Path::new("/srv/quests"), &location, 10);
+        assert!(unit.contains("\nType=notify\n"));
+        assert!(unit.contains("\nExecStart=/usr/local/bin/discord --config /srv/quests/config.toml --profile prod run\n"));
+        assert!(unit.contains("\nWorkingDirectory=/srv/quests\n"));
+        assert!(unit.contains("\nTimeoutStopSec=15\n"));
+
+        let unit = unit_file(Path::new("/usr/local/bin/discord"), Path::new("/srv/quests"), &ConfigLocation::default(), 10);
+        assert!(unit.contains("\nExecStart=/usr/local/bin/discord run\n"));
+    }
+}
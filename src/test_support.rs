@@ -0,0 +1,24 @@
+//! Fixtures shared by the unit tests
+
+use crate::config::Account;
+use crate::types::quest::DiscordQuest;
+use crate::types::secret::Secret;
+use chrono::{TimeZone, Utc};
+
+/// An account polling `en-US`, with its name as its token
+pub fn account(name: &str) -> Account {
+    Account {
+        name: name.to_string(),
+        token: Secret::new(name),
+        super_properties: String::new(),
+        regions: vec!["en-US".to_string()],
+    }
+}
+
+/// The sample quest with id `id`, active throughout January 2026
+pub fn quest(id: &str) -> DiscordQuest {
+    let mut quest = DiscordQuest::sample(Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap());
+    quest.id = id.to_string();
+    quest.config.expires_at = "2026-02-01T00:00:00+00:00".to_string();
+    quest
+}
@@ -0,0 +1,141 @@
+use crate::types::event::{QuestEvent, QuestEventKind};
+use crate::types::state::QuestState;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Reward type of quests without a reward
+const NO_REWARD_TYPE: &str = "No Reward";
+
+/// Summary of the active quests, sent on the `DIGEST_SCHEDULE`
+#[derive(Serialize, Clone, Debug)]
+pub struct QuestDigest {
+    /// Start of the period whose quests are listed as new
+    pub period_start: DateTime<Utc>,
+    pub generated_at: DateTime<Utc>,
+    /// Quests ending within this many hours are listed as ending soon
+    pub ending_hours: u32,
+    /// Number of active quests
+    pub active: usize,
+    /// Orbs available from all active quests
+    pub total_orbs: i64,
+    /// Quests expiring within `DIGEST_ENDING_HOURS`, soonest first
+    pub ending_soon: Vec<DigestQuest>,
+    /// Quests first seen since `period_start`, newest first
+    pub new_quests: Vec<DigestQuest>,
+    /// Every active quest by reward type, e.g. `Virtual Currency`
+    pub reward_types: BTreeMap<String, Vec<DigestQuest>>,
+}
+
+/// One quest listed in a digest
+#[derive(Serialize, Clone, Debug)]
+pub struct DigestQuest {
+    pub id: String,
+    pub game_title: String,
+    pub reward_name: Option<String>,
+    pub orb_quantity: Option<i32>,
+    pub expires_at_unix: Option<i64>,
+    pub quest_url: String,
+}
+
+impl QuestDigest {
+    /// Summarise the quests in `state` that are still active at `now`
+    #[must_use]
+    pub fn build(state: &QuestState, period_start: DateTime<Utc>, ending_hours: u32, now: DateTime<Utc>) -> Self {
+        let ending_before = (now + TimeDelta::hours(i64::from(ending_hours))).timestamp();
+        let mut digest = Self {
+            period_start,
+            generated_at: now,
+            ending_hours,
+            active: 0,
+            total_orbs: 0,
+            ending_soon: Vec::new(),
+            new_quests: Vec::new(),
+            reward_types: BTreeMap::new(),
+        };
+
+        for tracked in state.active_quests() {
+            let event = QuestEvent::new(QuestEventKind::Created, &tracked.quest, Vec::new(), now);
+            if event.computed.is_expired {
+                continue;
+            }
+            let quest = DigestQuest {
+                id: tracked.quest.id.clone(),
+                game_title: tracked.quest.config.messages.game_title.clone(),
+                reward_name: event.computed.reward_name,
+                orb_quantity: event.computed.orb_quantity,
+                expires_at_unix: event.computed.expires_at_unix,
+                quest_url: event.computed.quest_url,
+            };
+            digest.active += 1;
+            digest.total_orbs += i64::from(quest.orb_quantity.unwrap_or(0));
+            if quest.expires_at_unix.is_some_and(|ts| ts <= ending_before) {
+                digest.ending_soon.push(quest.clone());
+            }
+            if tracked.first_seen >= period_start {
+                digest.new_quests.push(quest.clone());
+            }
+            let reward_type = event.computed.reward_type.unwrap_or_else(|| NO_REWARD_TYPE.to_string());
+            digest.reward_types.entry(reward_type).or_default().push(quest);
+        }
+
+        digest.ending_soon.sort_by_key(|quest| quest.expires_at_unix);
+        digest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use crate::types::quest::DiscordQuest;
+    use crate::types::state::TrackedQuest;
+    use chrono::TimeZone;
+
+    fn quest(id: &str, expires_at: &str, reward: Option<(i32, i32)>) -> DiscordQuest {
+        let mut quest = test_support::quest(id);
+        quest.config.expires_at = expires_at.to_string();
+        let rewards = &mut quest.config.rewards_config.rewards;
+        match reward {
+            Some((reward_type, orbs)) => {
+                rewards[0].reward_type = reward_type;
+                rewards[0].orb_quantity = Some(orbs);
+            }
+            None => rewards.clear(),
+        }
+        quest
+    }
+
+    fn track(state: &mut QuestState, quest: DiscordQuest, first_seen: DateTime<Utc>) {
+        state.quests.insert(quest.id.clone(), TrackedQuest {
+            quest,
+            regions: ["en-US".to_string()].into(),
+            accounts: Default::default(),
+            user_status: Default::default(),
+            first_seen,
+            updated_at: first_seen,
+            expired: false,
+        });
+    }
+
+    #[test]
+    fn digest_summarises_active_quests() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 12, 9, 0, 0).unwrap();
+        let last_week = now - TimeDelta::days(7);
+        let mut state = QuestState::default();
+        track(&mut state, quest("1", "2026-10-13T00:00:00+00:00", Some((4, 700))), last_week - TimeDelta::days(1));
+        track(&mut state, quest("2", "2026-10-30T00:00:00+00:00", Some((4, 500))), now - TimeDelta::hours(2));
+        track(&mut state, quest("3", "2026-10-12T20:00:00+00:00", Some((3, 0))), now - TimeDelta::days(1));
+        track(&mut state, quest("4", "2026-10-20T00:00:00+00:00", None), last_week - TimeDelta::days(2));
+        track(&mut state, quest("5", "2026-10-01T00:00:00+00:00", Some((4, 900))), now - TimeDelta::hours(1));
+
+        let digest = QuestDigest::build(&state, last_week, 48, now);
+        assert_eq!(digest.active, 4, "quest 5 has already ended");
+        assert_eq!(digest.total_orbs, 1200);
+        let ids = |quests: &[DigestQuest]| quests.iter().map(|q| q.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&digest.ending_soon), ["3", "1"]);
+        assert_eq!(ids(&digest.new_quests), ["2", "3"]);
+        assert_eq!(digest.reward_types.keys().collect::<Vec<_>>(), ["Collectible", "No Reward", "Virtual Currency"]);
+        assert_eq!(ids(&digest.reward_types["Virtual Currency"]), ["2", "1"]);
+    }
+}
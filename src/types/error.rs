@@ -1,3 +1,4 @@
+use std::time::Duration;
 use thiserror::Error;
 
 /// Application error types
@@ -7,13 +8,29 @@ pub enum AppError {
     #[error("Configuration error: {0}")]
     Config(String),
     
+    /// Discord rejected the auth token (HTTP 401 or 403)
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
     /// HTTP request errors
     #[error("Request error: {0}")]
     Request(String),
+
+    /// Discord answered with HTTP 429; holds the `Retry-After` delay if it sent one
+    #[error("Rate limited: {0}")]
+    RateLimited(String, Option<Duration>),
     
     /// JSON parsing errors
     #[error("Parse error: {0}")]
     Parse(String),
+
+    /// A requested quest or resource does not exist
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    /// Filesystem errors (feed exports, state files)
+    #[error("I/O error: {0}")]
+    Io(String),
 }
 
 impl From<reqwest::Error> for AppError {
@@ -0,0 +1,149 @@
+use crate::types::constants::{
+    QUEST_URL_BASE, build_cdn_url, feature_id_to_name, reward_type_to_description,
+};
+use crate::types::quest::{DiscordQuest, QuestProgress};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Version of the outgoing JSON event schema. Bumped on breaking changes only.
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// Kind of change detected for a quest
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuestEventKind {
+    /// Quest was seen for the first time
+    #[serde(rename = "quest.created")]
+    Created,
+    /// Quest dates, rewards, tasks or assets changed since it was first seen
+    #[serde(rename = "quest.updated")]
+    Updated,
+    /// Quest passed its `expires_at` timestamp
+    #[serde(rename = "quest.expired")]
+    Expired,
+}
+
+impl QuestEventKind {
+    /// Wire name of the event, as used in the `event` field and headers
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Created => "quest.created",
+            Self::Updated => "quest.updated",
+            Self::Expired => "quest.expired",
+        }
+    }
+}
+
+/// A quest change event, serialized as the body of generic JSON notifications
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct QuestEvent {
+    pub schema_version: u32,
+    pub event: QuestEventKind,
+    pub occurred_at: DateTime<Utc>,
+    pub quest: DiscordQuest,
+    pub regions: Vec<String>,
+    /// Accounts that can see the quest; only filled in when several accounts are configured
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub accounts: Vec<String>,
+    /// Progress of each account that has seen the quest, e.g. `{"main": "enrolled"}`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub progress: BTreeMap<String, QuestProgress>,
+    pub computed: QuestComputed,
+}
+
+/// Fields derived from the raw quest so consumers don't have to re-implement them
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct QuestComputed {
+    pub quest_url: String,
+    pub banner_url: String,
+    pub game_tile_url: String,
+    pub starts_at_unix: Option<i64>,
+    pub expires_at_unix: Option<i64>,
+    pub is_expired: bool,
+    pub reward_type: Option<String>,
+    pub reward_name: Option<String>,
+    pub orb_quantity: Option<i32>,
+    pub features: Vec<String>,
+    pub tasks: Vec<ComputedTask>,
+}
+
+/// A single quest task with its target expressed in seconds
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ComputedTask {
+    pub event_name: String,
+    pub target_seconds: i32,
+}
+
+impl QuestEvent {
+    /// Build an event for `quest`, deriving the computed fields at `now`
+    #[must_use]
+    pub fn new(
+        kind: QuestEventKind,
+        quest: &DiscordQuest,
+        regions: Vec<String>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            schema_version: EVENT_SCHEMA_VERSION,
+            event: kind,
+            occurred_at: now,
+            quest: quest.clone(),
+            regions,
+            accounts: Vec::new(),
+            progress: BTreeMap::new(),
+            computed: QuestComputed::from_quest(quest, now),
+        }
+    }
+
+    /// Progress for notifications: `enrolled` with one account, `main: enrolled, alt: claimed`
+    /// with several
+    #[must_use]
+    pub fn progress_summary(&self) -> Option<String> {
+        match self.progress.len() {
+            0 => None,
+            1 => self.progress.values().next().map(|progress| progress.label().to_string()),
+            _ => Some(
+                self.progress.iter()
+                    .map(|(account, progress)| format!("{account}: {}", progress.label()))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+        }
+    }
+}
+
+impl QuestComputed {
+    #[must_use]
+    pub fn from_quest(quest: &DiscordQuest, now: DateTime<Utc>) -> Self {
+        let reward = quest.config.rewards_config.rewards.first();
+        let starts_at_unix = parse_timestamp(&quest.config.starts_at);
+        let expires_at_unix = parse_timestamp(&quest.config.expires_at);
+
+        Self {
+            quest_url: format!("{QUEST_URL_BASE}/{}", quest.id),
+            banner_url: build_cdn_url(&quest.id, &quest.config.assets.hero),
+            game_tile_url: build_cdn_url(&quest.id, &quest.config.assets.game_tile),
+            starts_at_unix,
+            expires_at_unix,
+            is_expired: expires_at_unix.is_some_and(|ts| ts <= now.timestamp()),
+            reward_type: reward.map(|r| reward_type_to_description(r.reward_type).to_string()),
+            reward_name: reward.map(|r| r.messages.name.clone()),
+            orb_quantity: reward.and_then(|r| r.orb_quantity),
+            features: quest.config.features
+                .iter()
+                .map(|id| feature_id_to_name(*id).to_string())
+                .collect(),
+            tasks: quest.config.task_config.tasks.values()
+                .map(|task| ComputedTask {
+                    event_name: task.event_name.clone(),
+                    target_seconds: task.target,
+                })
+                .collect(),
+        }
+    }
+}
+
+fn parse_timestamp(value: &str) -> Option<i64> {
+    DateTime::parse_from_rfc3339(value).ok().map(|dt| dt.timestamp())
+}
@@ -1,3 +1,7 @@
 pub mod error;
 pub mod quest;
 pub mod constants;
+pub mod event;
+pub mod state;
+pub mod secret;
+pub mod digest;
@@ -1,13 +1,118 @@
-use serde::Deserialize;
+use crate::types::constants::REWARD_TYPE_VIRTUAL_CURRENCY;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
-#[derive(Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+const SAMPLE_QUEST_ID: &str = "0000000000000000000";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct DiscordQuest {
     pub id: String,
     pub config: QuestConfig,
+    /// Progress of the account that fetched the quest; `null` until it enrolls.
+    /// Moved into the state per account, so stored quests do not carry it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_status: Option<QuestUserStatus>,
+}
+
+impl DiscordQuest {
+    /// A self-contained quest used by `test-webhook`, starting at `now` and ending a week later
+    #[must_use]
+    pub fn sample(now: DateTime<Utc>) -> Self {
+        let mut tasks = BTreeMap::new();
+        tasks.insert("WATCH_VIDEO".to_string(), QuestTask {
+            event_name: "WATCH_VIDEO".to_string(),
+            target: 900,
+        });
+
+        Self {
+            id: SAMPLE_QUEST_ID.to_string(),
+            config: QuestConfig {
+                starts_at: now.to_rfc3339(),
+                expires_at: (now + Duration::days(7)).to_rfc3339(),
+                features: vec![],
+                messages: QuestMessages {
+                    game_title: "Sample Quest".to_string(),
+                    game_publisher: "Discord Quest Notification".to_string(),
+                },
+                rewards_config: RewardsConfig {
+                    rewards: vec![QuestReward {
+                        reward_type: REWARD_TYPE_VIRTUAL_CURRENCY,
+                        messages: QuestRewardMessages { name: "700 Orbs".to_string() },
+                        sku_id: None,
+                        orb_quantity: Some(700),
+                        asset: None,
+                        quantity: None,
+                    }],
+                    platforms: vec![0],
+                },
+                assets: QuestAssets {
+                    hero: "quests/sample/hero.png".to_string(),
+                    game_tile: "quests/sample/game_tile.png".to_string(),
+                },
+                application: QuestApplication {
+                    id: SAMPLE_QUEST_ID.to_string(),
+                    name: "Sample Quest".to_string(),
+                    link: "https://discord.com/quests".to_string(),
+                },
+                task_config: QuestTaskConfig { config_type: 1, tasks },
+            },
+            user_status: None,
+        }
+    }
+}
+
+/// Per-user progress returned with each quest by `/quests/@me`
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct QuestUserStatus {
+    #[serde(default)]
+    pub enrolled_at: Option<String>,
+    #[serde(default)]
+    pub completed_at: Option<String>,
+    #[serde(default)]
+    pub claimed_at: Option<String>,
+}
+
+impl QuestUserStatus {
+    /// The furthest step the user has reached
+    #[must_use]
+    pub const fn progress(&self) -> QuestProgress {
+        if self.claimed_at.is_some() {
+            QuestProgress::Claimed
+        } else if self.completed_at.is_some() {
+            QuestProgress::Completed
+        } else if self.enrolled_at.is_some() {
+            QuestProgress::Enrolled
+        } else {
+            QuestProgress::NotEnrolled
+        }
+    }
+}
+
+/// How far an account got with a quest
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestProgress {
+    NotEnrolled,
+    Enrolled,
+    Completed,
+    Claimed,
+}
+
+impl QuestProgress {
+    /// Human-readable name used in notifications
+    #[must_use]
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::NotEnrolled => "not enrolled",
+            Self::Enrolled => "enrolled",
+            Self::Completed => "completed, not claimed",
+            Self::Claimed => "claimed",
+        }
+    }
 }
 
-#[derive(Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct QuestConfig {
     pub starts_at: String,
     pub expires_at: String,
@@ -19,45 +124,45 @@ pub struct QuestConfig {
     pub task_config: QuestTaskConfig,
 }
 
-#[derive(Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct QuestApplication {
     pub id: String,
     pub name: String,
     pub link: String,
 }
 
-#[derive(Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct QuestAssets {
     pub hero: String,
     pub game_tile: String,
 }
 
-#[derive(Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct QuestMessages {
     pub game_title: String,
     pub game_publisher: String,
 }
 
-#[derive(Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct QuestTaskConfig {
     #[serde(rename = "type")]
     pub config_type: i32,
     pub tasks: BTreeMap<String, QuestTask>,
 }
 
-#[derive(Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct QuestTask {
     pub event_name: String,
     pub target: i32,
 }
 
-#[derive(Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct RewardsConfig {
     pub rewards: Vec<QuestReward>,
     pub platforms: Vec<i32>,
 }
 
-#[derive(Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct QuestReward {
     #[serde(rename = "type")]
     pub reward_type: i32,
@@ -68,7 +173,7 @@ pub struct QuestReward {
     pub quantity: Option<i32>,
 }
 
-#[derive(Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct QuestRewardMessages {
     pub name: String,
 }
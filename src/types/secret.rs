@@ -0,0 +1,84 @@
+use std::fmt;
+
+const REDACTED: &str = "[REDACTED]";
+/// Longest response body excerpt included in errors and logs
+const MAX_BODY_EXCERPT: usize = 300;
+
+/// A credential, or a URL that embeds one. `Debug` and `Display` never print the value;
+/// use [`Secret::expose`] where it is actually sent.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    #[must_use]
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// The secret value, for use in requests
+    #[must_use]
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// Replace every occurrence of the secret in `text`, e.g. a response body that echoes it
+    #[must_use]
+    pub fn scrub(&self, text: &str) -> String {
+        if self.0.is_empty() {
+            text.to_string()
+        } else {
+            text.replace(&self.0, REDACTED)
+        }
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret({REDACTED})")
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+/// Shorten an HTTP response body for an error message and remove `secret` from it
+#[must_use]
+pub fn body_excerpt(body: &str, secret: &Secret) -> String {
+    let body = secret.scrub(body.trim());
+    match body.char_indices().nth(MAX_BODY_EXCERPT) {
+        Some((end, _)) => format!("{}...", &body[..end]),
+        None => body,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formatting_never_shows_the_value() {
+        let secret = Secret::new("hunter2");
+        assert_eq!(secret.to_string(), "[REDACTED]");
+        assert_eq!(format!("{secret:?}"), "Secret([REDACTED])");
+        assert_eq!(secret.expose(), "hunter2");
+    }
+
+    #[test]
+    fn body_excerpt_scrubs_and_truncates() {
+        let secret = Secret::new("tok");
+        assert_eq!(body_excerpt(" invalid tok ", &secret), "invalid [REDACTED]");
+        assert_eq!(body_excerpt("tok", &Secret::default()), "tok");
+
+        let long = "é".repeat(MAX_BODY_EXCERPT + 5);
+        assert_eq!(body_excerpt(&long, &secret).chars().count(), MAX_BODY_EXCERPT + 3);
+    }
+}
@@ -0,0 +1,111 @@
+use crate::types::error::AppError;
+use crate::types::event::QuestEvent;
+use crate::types::quest::{DiscordQuest, QuestProgress, QuestUserStatus};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// A quest the monitor has seen at least once
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TrackedQuest {
+    pub quest: DiscordQuest,
+    pub regions: BTreeSet<String>,
+    /// Names of the accounts that have seen the quest
+    #[serde(default)]
+    pub accounts: BTreeSet<String>,
+    /// Enrollment and completion of each account that has seen the quest
+    #[serde(default)]
+    pub user_status: BTreeMap<String, QuestUserStatus>,
+    pub first_seen: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub expired: bool,
+}
+
+impl TrackedQuest {
+    #[must_use]
+    pub fn regions_vec(&self) -> Vec<String> {
+        self.regions.iter().cloned().collect()
+    }
+
+    /// Progress of each account that has seen the quest
+    #[must_use]
+    pub fn progress(&self) -> BTreeMap<String, QuestProgress> {
+        self.user_status.iter().map(|(account, status)| (account.clone(), status.progress())).collect()
+    }
+}
+
+/// In-memory view of every quest seen so far, keyed by quest ID.
+/// Saved to `STATE_FILE` after each poll when configured.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct QuestState {
+    pub quests: BTreeMap<String, TrackedQuest>,
+    /// Deliveries that failed and are retried on later polls
+    #[serde(default)]
+    pub outbox: Vec<PendingDelivery>,
+    /// Notifications held during a destination's quiet hours
+    #[serde(default)]
+    pub held: Vec<HeldDelivery>,
+    /// Events detected by a poll whose notifications were not sent yet, oldest first;
+    /// left over when the monitor shut down in the middle of sending them
+    #[serde(default)]
+    pub undelivered: Vec<QuestEvent>,
+    /// When the last scheduled digest was sent; quests seen since then are new in the next one
+    #[serde(default)]
+    pub last_digest_at: Option<DateTime<Utc>>,
+}
+
+/// A notification waiting for the quiet hours of its destination to end
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HeldDelivery {
+    /// Label of the destination, see `Destination::label`
+    pub destination: String,
+    pub event: QuestEvent,
+    pub held_at: DateTime<Utc>,
+}
+
+/// A notification that could not be delivered to one destination
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PendingDelivery {
+    /// Label of the destination, see `Destination::label`
+    pub destination: String,
+    pub event: QuestEvent,
+    pub attempts: u32,
+    pub last_error: String,
+    pub last_attempt: DateTime<Utc>,
+}
+
+impl QuestState {
+    /// Quests that have not expired yet, most recently seen first
+    #[must_use]
+    pub fn active_quests(&self) -> Vec<&TrackedQuest> {
+        let mut active: Vec<&TrackedQuest> = self.quests.values().filter(|t| !t.expired).collect();
+        active.sort_by_key(|t| std::cmp::Reverse(t.first_seen));
+        active
+    }
+
+    /// Read state saved by a previous run, or `None` if the file does not exist yet
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Io` if the file cannot be read and `AppError::Parse` if it is corrupt
+    pub fn load(path: &Path) -> Result<Option<Self>, AppError> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(AppError::Io(format!("Failed to read state file '{}': {e}", path.display()))),
+        };
+        serde_json::from_str(&contents)
+            .map(Some)
+            .map_err(|e| AppError::Parse(format!("Invalid state file '{}': {e}", path.display())))
+    }
+
+    /// Serialize the state for saving to disk
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Parse` if serialization fails
+    pub fn to_json(&self) -> Result<String, AppError> {
+        serde_json::to_string_pretty(self).map_err(|e| AppError::Parse(format!("Failed to serialize state: {e}")))
+    }
+}
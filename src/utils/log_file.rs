@@ -0,0 +1,176 @@
+use crate::config::LogRotation;
+use chrono::{DateTime, Utc};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// A log file that is renamed to `<name>.<suffix>` when it rotates, keeping at most
+/// `max_files` rotated files next to it
+pub struct RollingFile {
+    path: PathBuf,
+    rotation: LogRotation,
+    max_files: usize,
+    state: Mutex<FileState>,
+}
+
+struct FileState {
+    file: File,
+    size: u64,
+    /// Time period the open file belongs to, for rotation by time
+    period: Option<String>,
+}
+
+/// Writes one log line to a [`RollingFile`]
+pub struct RollingWriter<'a>(&'a RollingFile);
+
+impl RollingFile {
+    /// Open `path` for appending, creating its directory
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if the directory or file cannot be created
+    pub fn open(path: &Path, rotation: LogRotation, max_files: usize) -> io::Result<Self> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let (file, size, modified) = open_append(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            rotation,
+            max_files,
+            state: Mutex::new(FileState { file, size, period: period(rotation, modified) }),
+        })
+    }
+
+    fn write_locked(&self, state: &mut FileState, buf: &[u8]) -> io::Result<usize> {
+        let now = Utc::now();
+        let due = match self.rotation {
+            LogRotation::Size(max) => state.size > 0 && state.size + buf.len() as u64 > max,
+            rotation => period(rotation, now) != state.period,
+        };
+        if due {
+            if let Err(e) = self.rotate(state, now) {
+                // Keep writing to the current file; retry on the next period or size limit
+                eprintln!("Failed to rotate log file {}: {e}", self.path.display());
+                state.period = period(self.rotation, now);
+                state.size = 0;
+            }
+        }
+        let written = state.file.write(buf)?;
+        state.size += written as u64;
+        Ok(written)
+    }
+
+    fn rotate(&self, state: &mut FileState, now: DateTime<Utc>) -> io::Result<()> {
+        state.file.flush()?;
+        let suffix = state.period.clone().unwrap_or_else(|| now.format("%Y%m%dT%H%M%S").to_string());
+        let mut target = self.rotated_path(&suffix);
+        let mut counter = 1;
+        while target.exists() {
+            target = self.rotated_path(&format!("{suffix}.{counter}"));
+            counter += 1;
+        }
+        fs::rename(&self.path, &target)?;
+
+        let (file, size, _) = open_append(&self.path)?;
+        *state = FileState { file, size, period: period(self.rotation, now) };
+        self.prune();
+        Ok(())
+    }
+
+    fn rotated_path(&self, suffix: &str) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{suffix}"));
+        self.path.with_file_name(name)
+    }
+
+    /// Delete the oldest rotated files beyond `max_files`
+    fn prune(&self) {
+        if self.max_files == 0 {
+            return;
+        }
+        let prefix = format!("{}.", self.path.file_name().unwrap_or_default().to_string_lossy());
+        let dir = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let Ok(entries) = fs::read_dir(dir) else { return };
+
+        let mut rotated: Vec<(std::time::SystemTime, PathBuf)> = entries
+            .flatten()
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+            .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+            .collect();
+        rotated.sort();
+        let excess = rotated.len().saturating_sub(self.max_files);
+        for (_, path) in rotated.into_iter().take(excess) {
+            if let Err(e) = fs::remove_file(&path) {
+                eprintln!("Failed to delete old log file {}: {e}", path.display());
+            }
+        }
+    }
+}
+
+impl Write for RollingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.0.state.lock().map_err(|_| io::Error::other("log file lock poisoned"))?;
+        self.0.write_locked(&mut state, buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut state = self.0.state.lock().map_err(|_| io::Error::other("log file lock poisoned"))?;
+        state.file.flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for RollingFile {
+    type Writer = RollingWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RollingWriter(self)
+    }
+}
+
+/// Suffix of the period `time` falls into; `None` for rotations not based on time
+fn period(rotation: LogRotation, time: DateTime<Utc>) -> Option<String> {
+    match rotation {
+        LogRotation::Hourly => Some(time.format("%Y-%m-%d-%H").to_string()),
+        LogRotation::Daily => Some(time.format("%Y-%m-%d").to_string()),
+        LogRotation::Never | LogRotation::Size(_) => None,
+    }
+}
+
+/// Open a file for appending, returning its size and last modification time
+fn open_append(path: &Path) -> io::Result<(File, u64, DateTime<Utc>)> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let metadata = file.metadata()?;
+    let modified = metadata.modified().map_or_else(|_| Utc::now(), DateTime::<Utc>::from);
+    Ok((file, metadata.len(), modified))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_rotation_keeps_max_files() {
+        let dir = std::env::temp_dir().join(format!("quest-logs-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let log = RollingFile::open(&dir.join("monitor.log"), LogRotation::Size(10), 2).expect("open log file");
+
+        for line in ["first line\n", "second line\n", "third line\n", "fourth line\n"] {
+            log.make_writer().write_all(line.as_bytes()).expect("write log line");
+        }
+
+        let mut names: Vec<String> = fs::read_dir(&dir).expect("read log dir")
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        let current = fs::read_to_string(dir.join("monitor.log")).expect("read current log");
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(current, "fourth line\n");
+        assert_eq!(names.len(), 3, "{names:?}");
+        assert!(names.iter().filter(|n| n.starts_with("monitor.log.")).count() == 2, "{names:?}");
+    }
+}
@@ -1,3 +1,4 @@
+mod log_file;
 mod tracing;
 
 pub use tracing::setup_logging;
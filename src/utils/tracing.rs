@@ -1,13 +1,54 @@
-use tracing_subscriber::EnvFilter;
+use crate::config::{LogFormat, LoggingConfig};
+use crate::types::error::AppError;
+use crate::utils::log_file::RollingFile;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter};
 
-pub fn setup_logging() {
+/// Initialise logging. One-off commands log only warnings and errors by default.
+/// Logs go to stderr when `stderr` is set, so stdout output can be piped; otherwise
+/// they go to `LOG_FILE` if configured, or stdout.
+///
+/// # Errors
+///
+/// Returns `AppError::Io` if the log file cannot be opened
+pub fn setup_logging(logging: &LoggingConfig, command_mode: bool, stderr: bool) -> Result<(), AppError> {
+    let default_level = if command_mode { "warn" } else { "info" };
     let filter = EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| EnvFilter::new("info"));
-    
-    tracing_subscriber::fmt()
-        .with_env_filter(filter)
-        .with_target(true)
-        .with_thread_ids(false)
-        .with_line_number(false)
-        .init();
-} 
\ No newline at end of file
+        .unwrap_or_else(|_| EnvFilter::new(default_level));
+
+    let (writer, ansi) = match &logging.file {
+        Some(path) if !command_mode => {
+            let file = RollingFile::open(path, logging.rotation, logging.max_files)
+                .map_err(|e| AppError::Io(format!("Failed to open LOG_FILE '{}': {e}", path.display())))?;
+            (BoxMakeWriter::new(file), false)
+        }
+        _ if stderr => (BoxMakeWriter::new(std::io::stderr), true),
+        _ => (BoxMakeWriter::new(std::io::stdout), true),
+    };
+
+    let registry = tracing_subscriber::registry().with(filter);
+    match logging.format {
+        LogFormat::Text => registry
+            .with(fmt::layer()
+                .with_target(true)
+                .with_thread_ids(false)
+                .with_line_number(false)
+                .with_ansi(ansi)
+                .with_writer(writer))
+            .init(),
+        // Span fields (account, region, quest_id, destination) are repeated on every
+        // line of the span, so each line can be filtered on its own
+        LogFormat::Json => registry
+            .with(fmt::layer()
+                .json()
+                .flatten_event(true)
+                .with_current_span(true)
+                .with_span_list(false)
+                .with_target(true)
+                .with_writer(writer))
+            .init(),
+    }
+    Ok(())
+}